@@ -260,7 +260,17 @@ const uploadZone = document.getElementById('upload-zone');
 const fileInput = document.getElementById('file-input');
 const uploadResults = document.getElementById('upload-results');
 const assetNameInput = document.getElementById('asset-name');
-const replaceNaNCheckbox = document.getElementById('replace-nan');
+const nanPolicySelect = document.getElementById('nan-policy');
+const flagOutliersCheckbox = document.getElementById('flag-outliers');
+
+// Processing options sent with processData/saveToDatabase; an empty outlier config uses the backend defaults.
+// Saving only replaces these fields, the asset's other stored options are kept.
+function processingOptions() {
+    return {
+        nan_policy: nanPolicySelect.value,
+        outliers: flagOutliersCheckbox && flagOutliersCheckbox.checked ? {} : null
+    };
+}
     
 
 if (uploadZone && fileInput) {
@@ -312,7 +322,7 @@ async function handleFileUpload(file) {
         // Validate required columns
         const requiredColumns = ['Date', 'Open', 'High', 'Low', 'Close'];
         const missingColumns = requiredColumns.filter(col => 
-            !readResult.data.some(row => row.hasOwnProperty(col))
+            !readResult.data.some(row => row[col] != null)
         );
         
         if (missingColumns.length > 0) {
@@ -322,7 +332,11 @@ async function handleFileUpload(file) {
         // Process data
         const processResult = await window.electronAPI.processData(
             readResult.data,
-            replaceNaNCheckbox.checked
+            nanPolicySelect.value === 'zero',
+            undefined,
+            undefined,
+            undefined,
+            processingOptions()
         );
         
         if (!processResult.success) {
@@ -461,7 +475,8 @@ async function saveToDatabase() {
         return;
     }
     
-    const result = await window.electronAPI.saveToDatabase(currentProcessedData, assetName);
+    // No indicator list: the backend keeps (and computes) the indicators already saved for the asset
+    const result = await window.electronAPI.saveToDatabase(currentProcessedData, assetName, undefined, processingOptions());
     
     if (result.success) {
         const successDiv = document.createElement('div');
@@ -487,7 +502,7 @@ async function saveToDatabase() {
 async function downloadExcel(data, assetName) {
     const result = await window.electronAPI.showSaveDialog(`${assetName}_processed.xlsx`);
     if (!result.canceled) {
-        await window.electronAPI.exportExcel(data, result.filePath);
+        await window.electronAPI.exportExcel(data, result.filePath, assetName);
         alert('File saved successfully!');
     }
 }
@@ -649,6 +664,8 @@ async function loadAssetAnalysis() {
             const res = await window.electronAPI.addRow(assetName, newRow);
             if (res && res.success) {
                 await loadAssetAnalysis();
+                // The edit is saved; synthetic assets built from this one may not have been rebuilt
+                if (res.message) alert(res.message);
             } else {
                 alert('Error adding row: ' + (res.error || 'unknown'));
             }
@@ -672,6 +689,7 @@ async function loadAssetAnalysis() {
             const res = await window.electronAPI.deleteRow(assetName, rowId);
             if (res && res.success) {
                 await loadAssetAnalysis();
+                if (res.message) alert(res.message);
             } else {
                 alert('Error deleting row: ' + (res && res.error ? res.error : 'unknown'));
             }
@@ -1099,22 +1117,35 @@ async function saveEditedRow(rowId) {
     const lowInput = tr.querySelector('.edit-Low');
     const closeInput = tr.querySelector('.edit-Close');
 
-    const updated = {};
-    if (dateInput) updated.Date = dateInput.value;
-    if (openInput) updated.Open = parseFloat(openInput.value);
-    if (highInput) updated.High = parseFloat(highInput.value);
-    if (lowInput) updated.Low = parseFloat(lowInput.value);
-    if (closeInput) updated.Close = parseFloat(closeInput.value);
+    const values = {};
+    if (dateInput) values.Date = dateInput.value;
+    if (openInput) values.Open = parseFloat(openInput.value);
+    if (highInput) values.High = parseFloat(highInput.value);
+    if (lowInput) values.Low = parseFloat(lowInput.value);
+    if (closeInput) values.Close = parseFloat(closeInput.value);
 
     // Basic validation
-    if (!updated.Date || isNaN(updated.Open) || isNaN(updated.High) || isNaN(updated.Low) || isNaN(updated.Close)) {
+    if (!values.Date || isNaN(values.Open) || isNaN(values.High) || isNaN(values.Low) || isNaN(values.Close)) {
         alert('Please provide valid Date, Open, High, Low, and Close values.');
         return;
     }
 
+    // Send only the fields that changed: the table shows rounded, adjusted prices, and resending them
+    // would overwrite the stored values
+    const updated = {};
+    const inputs = { Date: dateInput, Open: openInput, High: highInput, Low: lowInput, Close: closeInput };
+    for (const [col, input] of Object.entries(inputs)) {
+        if (input && input.value !== input.defaultValue) updated[col] = values[col];
+    }
+    if (Object.keys(updated).length === 0) {
+        cancelEditRow(rowId);
+        return;
+    }
+
     const res = await window.electronAPI.updateRow(assetName, rowId, updated);
     if (res && res.success) {
         await loadAssetAnalysis();
+        if (res.message) alert(res.message);
     } else {
         alert('Error updating row: ' + (res.error || 'unknown'));
     }
@@ -41,9 +41,10 @@
     readExcelFile: (filePath) => invoke('read_excel_file', { filePath }),
     readExcelBuffer: (buffer) => invoke('read_excel_buffer', { buffer }),
 
-    processData: (data, replaceNaN) => invoke('process_data', { data, replaceNan: replaceNaN }),
+    processData: (data, replaceNaN, indicators, fillPolicy, calendar, options) =>
+      invoke('process_data', { data, replaceNan: replaceNaN, indicators, fillPolicy, calendar, options }),
 
-    saveToDatabase: (data, assetName) => invoke('save_to_database', { data, assetName }),
+    saveToDatabase: (data, assetName, indicators, options) => invoke('save_to_database', { data, assetName, indicators, options }),
     addRow: (assetName, row) => invoke('add_row', { assetName, newRow: row }),
     updateRow: (assetName, rowId, updatedRow) => invoke('update_row', { assetName, rowId, updatedRow }),
     deleteRow: (assetName, rowId) => invoke('delete_row', { assetName, rowId }),
@@ -52,10 +53,41 @@
     getAssetData: (assetName) => invoke('get_asset_data', { assetName }),
     getDatabaseStats: () => invoke('get_database_stats'),
     getAssetDateRange: (assetName) => invoke('get_asset_date_range', { assetName }),
+    findAnalogYears: (assetName, topN) => invoke('find_analog_years', { assetName, topN }),
+    getSeasonalForecast: (assetName, lookbacks, percentiles) =>
+      invoke('get_seasonal_forecast', { assetName, lookbacks, percentiles }),
+    getSeasonalBands: (assetName, basis, resamples, confidence, seed) =>
+      invoke('get_seasonal_bands', { assetName, basis, resamples, confidence, seed }),
+    getFilteredSeasonality: (assetName, filter, basis) =>
+      invoke('get_filtered_seasonality', { assetName, filter, basis }),
+    runSeasonalScreener: (config, assets) => invoke('run_seasonal_screener', { config, assets }),
+    findBestWindows: (assetName, search) => invoke('find_best_windows', { assetName, search }),
+    runBacktest: (assetName, config) => invoke('run_backtest', { assetName, config }),
+    getVolumeSeasonality: (assetName) => invoke('get_volume_seasonality', { assetName }),
+    getRiskSeasonality: (assetName) => invoke('get_risk_seasonality', { assetName }),
+    exportSeasonalSummary: (assetName, filePath) => invoke('export_seasonal_summary', { assetName, filePath }),
+    resampleAsset: (assetName, period, saveAs) => invoke('resample_asset', { assetName, period, saveAs }),
+    getIntradaySeasonality: (assetName, sessions) => invoke('get_intraday_seasonality', { assetName, sessions }),
+    detectGaps: (assetName) => invoke('detect_gaps', { assetName }),
+    getCalendars: () => invoke('get_calendars'),
+    saveCalendar: (calendar) => invoke('save_calendar', { calendar }),
+    setAssetCalendar: (assetName, calendar) => invoke('set_asset_calendar', { assetName, calendar }),
+    getHolidayEffects: (assetName, calendar) => invoke('get_holiday_effects', { assetName, calendar }),
+    runEventStudy: (assetName, events, daysBefore, daysAfter, confidence) =>
+      invoke('run_event_study', { assetName, events, daysBefore, daysAfter, confidence }),
+    getCorporateActions: (assetName) => invoke('get_corporate_actions', { assetName }),
+    saveCorporateActions: (assetName, actions) => invoke('save_corporate_actions', { assetName, actions }),
+    buildContinuousFutures: (assetName, contracts, rule, adjustment) =>
+      invoke('build_continuous_futures', { assetName, contracts, rule, adjustment }),
+    getRollDates: (assetName) => invoke('get_roll_dates', { assetName }),
+    defineSyntheticAsset: (assetName, formula) => invoke('define_synthetic_asset', { assetName, formula }),
+    getSyntheticDefinition: (assetName) => invoke('get_synthetic_definition', { assetName }),
+    getCorrelationMatrix: (assetNames, window) => invoke('get_correlation_matrix', { assetNames, window }),
+    exportCorrelationExcel: (matrix, filePath) => invoke('export_correlation_excel', { matrix, filePath }),
 
     clearDatabase: () => invoke('clear_database'),
 
-    exportExcel: (data, filePath) => invoke('export_excel', { data, filePath }),
+    exportExcel: (data, filePath, assetName, options) => invoke('export_excel', { data, filePath, assetName, options }),
     readFile: (filePath) => invoke('read_file', { filePath })
   };
 })();
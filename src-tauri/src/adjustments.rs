@@ -0,0 +1,237 @@
+use crate::model::OhlcBar;
+use crate::processor::as_f64;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Columns rescaled by back-adjustment; the unadjusted values are kept under `Raw_<column>`.
+pub const ADJUSTED_COLUMNS: [&str; 5] = ["Open", "High", "Low", "Close", "Volume"];
+pub const RAW_PREFIX: &str = "Raw_";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ActionKind {
+    /// New shares per old share, e.g. 2.0 for a 2-for-1 split or 0.1 for a 1-for-10 reverse split.
+    Split { ratio: f64 },
+    /// Cash paid per share; prices before the ex-date are scaled by 1 - amount / previous close.
+    Dividend { amount: f64 },
+}
+
+impl ActionKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ActionKind::Split { .. } => "split",
+            ActionKind::Dividend { .. } => "dividend",
+        }
+    }
+
+    pub fn value(&self) -> f64 {
+        match self {
+            ActionKind::Split { ratio } => *ratio,
+            ActionKind::Dividend { amount } => *amount,
+        }
+    }
+
+    pub fn from_parts(name: &str, value: f64) -> anyhow::Result<Self> {
+        match name {
+            "split" => Ok(ActionKind::Split { ratio: value }),
+            "dividend" => Ok(ActionKind::Dividend { amount: value }),
+            other => Err(anyhow::anyhow!("Unknown corporate action type '{}'", other)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CorporateAction {
+    /// Ex-date (YYYY-MM-DD); bars before it are adjusted.
+    pub date: String,
+    #[serde(flatten)]
+    pub kind: ActionKind,
+}
+
+pub fn raw_key(column: &str) -> String {
+    format!("{}{}", RAW_PREFIX, column)
+}
+
+/// Checks dates and values before actions are stored.
+pub fn validate(actions: &[CorporateAction]) -> anyhow::Result<()> {
+    for a in actions {
+        if NaiveDate::parse_from_str(&a.date, "%Y-%m-%d").is_err() {
+            return Err(anyhow::anyhow!("Invalid ex-date '{}', expected YYYY-MM-DD", a.date));
+        }
+        let v = a.kind.value();
+        if !v.is_finite() || v <= 0.0 {
+            return Err(anyhow::anyhow!("{} on {} needs a positive value", a.kind.name(), a.date));
+        }
+    }
+    Ok(())
+}
+
+/// The bar's fields for `ADJUSTED_COLUMNS`, in the same order.
+fn adjusted_fields(bar: &mut OhlcBar) -> [&mut Option<f64>; 5] {
+    [&mut bar.open, &mut bar.high, &mut bar.low, &mut bar.close, &mut bar.volume]
+}
+
+/// Puts any previously kept raw values back into the price columns, so adjustment always starts from the raw series.
+fn restore_raw(bar: &mut OhlcBar) {
+    let mut extra = std::mem::take(&mut bar.extra);
+    for (column, value) in ADJUSTED_COLUMNS.into_iter().zip(adjusted_fields(bar)) {
+        if let Some(raw) = extra.remove(&raw_key(column)) {
+            *value = as_f64(&raw).filter(|v| v.is_finite());
+        }
+    }
+    bar.extra = extra;
+}
+
+/// The bars with any back-adjustment undone.
+pub fn raw_prices(input: &[OhlcBar]) -> Vec<OhlcBar> {
+    let mut bars = input.to_vec();
+    bars.iter_mut().for_each(restore_raw);
+    bars
+}
+
+/// Applies a partial edit made to a stored bar as displayed, i.e. with adjusted prices. Edited columns that
+/// carry a raw copy are converted back to raw with the bar's own factor, so re-adjusting does not apply it twice.
+pub fn apply_edit(stored: &OhlcBar, updates: Map<String, Value>) -> serde_json::Result<OhlcBar> {
+    let mut edited = stored.to_map();
+    for column in ADJUSTED_COLUMNS {
+        let raw = raw_key(column);
+        let Some(value) = updates.get(column) else { continue };
+        if updates.contains_key(&raw) {
+            continue;
+        }
+        let factor = match (edited.get(&raw).and_then(as_f64), edited.get(column).and_then(as_f64)) {
+            (Some(r), Some(a)) if a != 0.0 => Some(r / a),
+            _ => None,
+        };
+        match (factor, as_f64(value)) {
+            (Some(f), Some(v)) => edited.insert(raw, (v * f).into()),
+            _ => edited.remove(&raw),
+        };
+    }
+    edited.extend(updates);
+    OhlcBar::from_map(&edited)
+}
+
+/// Back-adjusts OHLC (and volume for splits) so the latest prices are unchanged and earlier bars are comparable.
+/// Bars keep their order; with actions present every bar also carries its raw values under `Raw_*`.
+pub fn adjust_prices(input: &[OhlcBar], actions: &[CorporateAction]) -> anyhow::Result<Vec<OhlcBar>> {
+    validate(actions)?;
+    let mut bars = raw_prices(input);
+    if actions.is_empty() {
+        return Ok(bars);
+    }
+
+    let mut closes: Vec<(NaiveDate, f64)> = bars.iter().filter_map(|b| Some((b.parsed_date()?, b.close?))).collect();
+    closes.sort_by_key(|c| c.0);
+
+    // (ex-date, price factor, volume factor)
+    let mut factors: Vec<(NaiveDate, f64, f64)> = Vec::with_capacity(actions.len());
+    for a in actions {
+        let ex_date = NaiveDate::parse_from_str(&a.date, "%Y-%m-%d")?;
+        match a.kind {
+            ActionKind::Split { ratio } => factors.push((ex_date, 1.0 / ratio, ratio)),
+            ActionKind::Dividend { amount } => {
+                let prev = closes.iter().rev().find(|(d, _)| *d < ex_date).map(|c| c.1);
+                match prev {
+                    Some(close) if close > amount => factors.push((ex_date, 1.0 - amount / close, 1.0)),
+                    Some(_) => return Err(anyhow::anyhow!("Dividend on {} is not smaller than the previous close", a.date)),
+                    // No bars before the ex-date, nothing to adjust.
+                    None => {}
+                }
+            }
+        }
+    }
+
+    for bar in &mut bars {
+        let Some(date) = bar.parsed_date() else { continue };
+        let (price_factor, volume_factor) = factors
+            .iter()
+            .filter(|(ex_date, _, _)| date < *ex_date)
+            .fold((1.0, 1.0), |(p, v), (_, pf, vf)| (p * pf, v * vf));
+
+        let mut extra = std::mem::take(&mut bar.extra);
+        for (column, value) in ADJUSTED_COLUMNS.into_iter().zip(adjusted_fields(bar)) {
+            let Some(raw) = *value else { continue };
+            let factor = if column == "Volume" { volume_factor } else { price_factor };
+            *value = Some(raw * factor);
+            extra.insert(raw_key(column), raw.into());
+        }
+        bar.extra = extra;
+    }
+    Ok(bars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::test_bar;
+
+    fn bar(date: &str, close: f64) -> OhlcBar {
+        OhlcBar { volume: Some(1000.0), ..test_bar(date, close).bar }
+    }
+
+    fn split(date: &str, ratio: f64) -> CorporateAction {
+        CorporateAction { date: date.to_string(), kind: ActionKind::Split { ratio } }
+    }
+
+    #[test]
+    fn split_scales_bars_before_the_ex_date() {
+        let input = [bar("2024-01-02", 100.0), bar("2024-01-03", 51.0)];
+        let out = adjust_prices(&input, &[split("2024-01-03", 2.0)]).unwrap();
+        assert_eq!(out[0].close, Some(50.0));
+        assert_eq!(out[0].volume, Some(2000.0));
+        assert_eq!(out[0].extra.get("Raw_Close").and_then(|v| v.as_f64()), Some(100.0));
+        assert_eq!(out[1].close, Some(51.0));
+        assert_eq!(out[1].volume, Some(1000.0));
+    }
+
+    #[test]
+    fn readjusting_adjusted_bars_is_idempotent() {
+        let input = [bar("2024-01-02", 100.0), bar("2024-01-03", 51.0)];
+        let actions = [split("2024-01-03", 2.0)];
+        let once = adjust_prices(&input, &actions).unwrap();
+        assert_eq!(adjust_prices(&once, &actions).unwrap(), once);
+    }
+
+    #[test]
+    fn clearing_actions_restores_raw_prices() {
+        let input = [bar("2024-01-02", 100.0), bar("2024-01-03", 51.0)];
+        let adjusted = adjust_prices(&input, &[split("2024-01-03", 2.0)]).unwrap();
+        assert_eq!(adjust_prices(&adjusted, &[]).unwrap(), input);
+    }
+
+    #[test]
+    fn dividend_uses_the_previous_close() {
+        let input = [bar("2024-01-02", 50.0), bar("2024-01-03", 49.0)];
+        let actions = [CorporateAction { date: "2024-01-03".to_string(), kind: ActionKind::Dividend { amount: 1.0 } }];
+        let out = adjust_prices(&input, &actions).unwrap();
+        assert_eq!(out[0].close, Some(49.0));
+        assert_eq!(out[0].volume, Some(1000.0));
+    }
+
+    #[test]
+    fn editing_a_pre_split_row_stores_raw_prices() {
+        let actions = [split("2024-01-03", 2.0)];
+        let adjusted = adjust_prices(&[bar("2024-01-02", 100.0), bar("2024-01-03", 51.0)], &actions).unwrap();
+        // The table shows the adjusted 50.0; the user corrects the close to 52.0 and the volume to 3000.
+        let updates = serde_json::json!({ "Close": 52.0, "Volume": 3000.0 });
+        let edited = apply_edit(&adjusted[0], updates.as_object().unwrap().clone()).unwrap();
+        assert_eq!(edited.extra.get("Raw_Close").and_then(|v| v.as_f64()), Some(104.0));
+        assert_eq!(edited.extra.get("Raw_Volume").and_then(|v| v.as_f64()), Some(1500.0));
+        assert_eq!(edited.extra.get("Raw_Open").and_then(|v| v.as_f64()), Some(100.0));
+
+        let readjusted = adjust_prices(&[edited, adjusted[1].clone()], &actions).unwrap();
+        assert_eq!(readjusted[0].close, Some(52.0));
+        assert_eq!(readjusted[0].volume, Some(3000.0));
+        assert_eq!(readjusted[0].open, Some(50.0));
+    }
+
+    #[test]
+    fn editing_an_unadjusted_row_keeps_the_value() {
+        let updates = serde_json::json!({ "Close": 52.0 });
+        let edited = apply_edit(&bar("2024-01-02", 100.0), updates.as_object().unwrap().clone()).unwrap();
+        assert_eq!(edited.close, Some(52.0));
+        assert!(edited.extra.is_empty());
+    }
+}
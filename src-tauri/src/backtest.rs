@@ -0,0 +1,316 @@
+use crate::model::ProcessedBar;
+use crate::processor::mean;
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EntryExitRule {
+    /// Hold between two calendar dates ("MM-DD") every year; the window may wrap past year end.
+    Calendar { entry: String, exit: String },
+    /// Enter when the walk-forward seasonal score rises to `entry_above`, exit when it falls to `exit_below`.
+    /// The score is on the 0-100 scale of True_Seasonal but, unlike that column, is built from earlier years only.
+    Seasonal { entry_above: f64, exit_below: f64 },
+}
+
+#[derive(Deserialize)]
+pub struct BacktestConfig {
+    pub rule: EntryExitRule,
+    #[serde(default)]
+    pub short: bool,
+    #[serde(default = "default_initial_capital")]
+    pub initial_capital: f64,
+}
+
+fn default_initial_capital() -> f64 {
+    10_000.0
+}
+
+#[derive(Serialize)]
+pub struct Trade {
+    pub entry_date: String,
+    pub exit_date: String,
+    pub entry_price: f64,
+    pub exit_price: f64,
+    pub return_pct: f64,
+    pub bars_held: usize,
+}
+
+#[derive(Serialize)]
+pub struct EquityPoint {
+    pub date: String,
+    pub equity: f64,
+}
+
+#[derive(Serialize)]
+pub struct BacktestResult {
+    pub trades: Vec<Trade>,
+    pub equity_curve: Vec<EquityPoint>,
+    pub total_trades: usize,
+    pub win_rate: f64,
+    pub total_return_pct: f64,
+    pub max_drawdown_pct: f64,
+    pub cagr_pct: f64,
+}
+
+struct Bar {
+    date: NaiveDate,
+    close: f64,
+    seasonal: Option<f64>,
+}
+
+pub(crate) fn parse_month_day(s: &str) -> anyhow::Result<(u32, u32)> {
+    let (m, d) = s
+        .trim()
+        .split_once('-')
+        .ok_or_else(|| anyhow::anyhow!("Invalid calendar date '{}', expected MM-DD", s))?;
+    let month: u32 = m.parse()?;
+    let day: u32 = d.parse()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return Err(anyhow::anyhow!("Invalid calendar date '{}', expected MM-DD", s));
+    }
+    Ok((month, day))
+}
+
+fn in_calendar_window(date: NaiveDate, entry: (u32, u32), exit: (u32, u32)) -> bool {
+    let md = (date.month(), date.day());
+    if entry <= exit {
+        md >= entry && md < exit
+    } else {
+        md >= entry || md < exit
+    }
+}
+
+fn bars_from_rows(rows: &[ProcessedBar]) -> Vec<Bar> {
+    let mut bars: Vec<Bar> = rows
+        .iter()
+        .filter_map(|row| {
+            let date = row.bar.parsed_date()?;
+            let close = row.bar.close.filter(|c| *c > 0.0)?;
+            Some(Bar { date, close, seasonal: None })
+        })
+        .collect();
+    bars.sort_by_key(|b| b.date);
+    bars
+}
+
+/// Scores each bar's month 0-100 by its average daily return over the years before the bar's year, min-max
+/// scaled across months. The first year, and years whose months all score alike, get no score.
+fn walk_forward_seasonal(bars: &mut [Bar]) {
+    // Mean daily % return per (year, month); a bar's return is measured from the previous bar's close.
+    let mut month_returns: BTreeMap<i32, [(f64, usize); 12]> = BTreeMap::new();
+    for pair in bars.windows(2) {
+        let month = &mut month_returns.entry(pair[1].date.year()).or_insert([(0.0, 0); 12])[pair[1].date.month0() as usize];
+        month.0 += (pair[1].close / pair[0].close - 1.0) * 100.0;
+        month.1 += 1;
+    }
+
+    let mut scores: BTreeMap<i32, [Option<f64>; 12]> = BTreeMap::new();
+    let mut prior: [Vec<f64>; 12] = Default::default();
+    let first_year = bars.first().map(|b| b.date.year()).unwrap_or_default();
+    let last_year = bars.last().map(|b| b.date.year()).unwrap_or_default();
+    for year in first_year..=last_year {
+        let profile: Vec<Option<f64>> = prior.iter().map(|v| mean(v)).collect();
+        let known = profile.iter().flatten();
+        let (lo, hi) = known.fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| (lo.min(*v), hi.max(*v)));
+        if hi > lo {
+            scores.insert(year, std::array::from_fn(|m| profile[m].map(|v| (v - lo) / (hi - lo) * 100.0)));
+        }
+        // Only now does this year's data join the history used for later years.
+        if let Some(months) = month_returns.get(&year) {
+            for (history, (sum, count)) in prior.iter_mut().zip(months) {
+                if *count > 0 {
+                    history.push(sum / *count as f64);
+                }
+            }
+        }
+    }
+
+    for bar in bars {
+        bar.seasonal = scores.get(&bar.date.year()).and_then(|s| s[bar.date.month0() as usize]);
+    }
+}
+
+/// Simulates a single-position strategy over the stored history, entering and exiting at the close.
+pub fn run_backtest(rows: &[ProcessedBar], config: &BacktestConfig) -> anyhow::Result<BacktestResult> {
+    if !config.initial_capital.is_finite() || config.initial_capital <= 0.0 {
+        return Err(anyhow::anyhow!("Initial capital must be positive"));
+    }
+    let calendar = match &config.rule {
+        EntryExitRule::Calendar { entry, exit } => Some((parse_month_day(entry)?, parse_month_day(exit)?)),
+        EntryExitRule::Seasonal { .. } => None,
+    };
+    let mut bars = bars_from_rows(rows);
+    if calendar.is_none() {
+        walk_forward_seasonal(&mut bars);
+    }
+    let direction = if config.short { -1.0 } else { 1.0 };
+
+    let mut trades: Vec<Trade> = Vec::new();
+    let mut equity_curve: Vec<EquityPoint> = Vec::with_capacity(bars.len());
+    let mut equity = config.initial_capital;
+    // (bar index of entry, equity at entry)
+    let mut open: Option<(usize, f64)> = None;
+
+    for (i, bar) in bars.iter().enumerate() {
+        let wants_position = match (&config.rule, calendar) {
+            (EntryExitRule::Calendar { .. }, Some((entry, exit))) => in_calendar_window(bar.date, entry, exit),
+            (EntryExitRule::Seasonal { entry_above, exit_below }, _) => match bar.seasonal {
+                Some(v) if open.is_some() => v > *exit_below,
+                Some(v) => v >= *entry_above,
+                None => open.is_some(),
+            },
+            _ => false,
+        };
+
+        if let Some((entry_idx, entry_equity)) = open {
+            let entry_price = bars[entry_idx].close;
+            let ret = direction * (bar.close / entry_price - 1.0);
+            equity = entry_equity * (1.0 + ret);
+
+            if !wants_position || i == bars.len() - 1 {
+                trades.push(Trade {
+                    entry_date: bars[entry_idx].date.format("%Y-%m-%d").to_string(),
+                    exit_date: bar.date.format("%Y-%m-%d").to_string(),
+                    entry_price,
+                    exit_price: bar.close,
+                    return_pct: ret * 100.0,
+                    bars_held: i - entry_idx,
+                });
+                open = None;
+            }
+        } else if wants_position && i < bars.len() - 1 {
+            open = Some((i, equity));
+        }
+
+        equity_curve.push(EquityPoint {
+            date: bar.date.format("%Y-%m-%d").to_string(),
+            equity,
+        });
+    }
+
+    let wins = trades.iter().filter(|t| t.return_pct > 0.0).count();
+    let win_rate = if trades.is_empty() { 0.0 } else { wins as f64 / trades.len() as f64 * 100.0 };
+
+    let mut peak = config.initial_capital;
+    let mut max_drawdown = 0.0_f64;
+    for p in &equity_curve {
+        peak = peak.max(p.equity);
+        if peak > 0.0 {
+            max_drawdown = max_drawdown.max((peak - p.equity) / peak * 100.0);
+        }
+    }
+
+    let total_return_pct = (equity / config.initial_capital - 1.0) * 100.0;
+    let cagr_pct = match (bars.first(), bars.last()) {
+        (Some(first), Some(last)) if last.date > first.date && equity > 0.0 => {
+            let years = (last.date - first.date).num_days() as f64 / 365.25;
+            ((equity / config.initial_capital).powf(1.0 / years) - 1.0) * 100.0
+        }
+        _ => 0.0,
+    };
+
+    Ok(BacktestResult {
+        total_trades: trades.len(),
+        trades,
+        equity_curve,
+        win_rate,
+        total_return_pct,
+        max_drawdown_pct: max_drawdown,
+        cagr_pct,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::test_bar;
+
+    fn calendar_config(short: bool) -> BacktestConfig {
+        BacktestConfig {
+            rule: EntryExitRule::Calendar { entry: "01-02".into(), exit: "01-04".into() },
+            short,
+            initial_capital: 10_000.0,
+        }
+    }
+
+    fn daily(closes: &[f64]) -> Vec<ProcessedBar> {
+        closes.iter().enumerate().map(|(i, c)| test_bar(&format!("2024-01-{:02}", i + 1), *c)).collect()
+    }
+
+    #[test]
+    fn long_trade_compounds_from_the_entry_close() {
+        let result = run_backtest(&daily(&[100.0, 110.0, 99.0, 120.0]), &calendar_config(false)).unwrap();
+        assert_eq!(result.total_trades, 1);
+        let trade = &result.trades[0];
+        assert_eq!((trade.entry_date.as_str(), trade.exit_date.as_str(), trade.bars_held), ("2024-01-02", "2024-01-04", 2));
+        assert!((trade.return_pct - 100.0 / 11.0).abs() < 1e-9);
+        let equity: Vec<f64> = result.equity_curve.iter().map(|p| p.equity).collect();
+        let expected = [10_000.0, 10_000.0, 9_000.0, 120_000.0 / 11.0];
+        for (e, x) in equity.iter().zip(expected) {
+            assert!((e - x).abs() < 1e-6, "{:?}", equity);
+        }
+        assert!((result.total_return_pct - 100.0 / 11.0).abs() < 1e-9);
+        assert!((result.max_drawdown_pct - 10.0).abs() < 1e-9);
+        assert_eq!(result.win_rate, 100.0);
+    }
+
+    #[test]
+    fn short_trade_gains_when_the_price_falls() {
+        let result = run_backtest(&daily(&[100.0, 110.0, 99.0, 120.0]), &calendar_config(true)).unwrap();
+        assert!((result.trades[0].return_pct + 100.0 / 11.0).abs() < 1e-9);
+        assert!((result.equity_curve[2].equity - 11_000.0).abs() < 1e-6);
+        let last = 100_000.0 / 11.0;
+        assert!((result.equity_curve[3].equity - last).abs() < 1e-6);
+        assert!((result.max_drawdown_pct - (11_000.0 - last) / 11_000.0 * 100.0).abs() < 1e-9);
+        assert_eq!(result.win_rate, 0.0);
+    }
+
+    /// Bars on the 1st and 15th of each month; each bar moves the price by the year's factor for its half-year.
+    fn half_years(years: &[(i32, f64, f64)]) -> Vec<ProcessedBar> {
+        let mut price = 100.0;
+        let mut rows = Vec::new();
+        for (year, first_half, second_half) in years {
+            for month in 1..=12 {
+                for day in [1, 15] {
+                    price *= if month <= 6 { first_half } else { second_half };
+                    let mut row = test_bar(&format!("{}-{:02}-{:02}", year, month, day), price);
+                    // The stored column would look ahead; the backtest must not use it.
+                    row.true_seasonal = Some(100.0);
+                    rows.push(row);
+                }
+            }
+        }
+        rows
+    }
+
+    fn seasonal_config() -> BacktestConfig {
+        BacktestConfig {
+            rule: EntryExitRule::Seasonal { entry_above: 50.0, exit_below: 50.0 },
+            short: false,
+            initial_capital: 10_000.0,
+        }
+    }
+
+    fn dated(trades: &[Trade]) -> Vec<(&str, &str)> {
+        trades.iter().map(|t| (t.entry_date.as_str(), t.exit_date.as_str())).collect()
+    }
+
+    #[test]
+    fn seasonal_signals_come_from_earlier_years_only() {
+        let rows = half_years(&[(2020, 1.01, 0.99), (2021, 1.01, 0.99), (2022, 0.99, 1.01)]);
+        let result = run_backtest(&rows, &seasonal_config()).unwrap();
+        // Nothing trades in 2020: there is no earlier year to learn from.
+        assert_eq!(dated(&result.trades), vec![("2021-01-01", "2021-07-01"), ("2022-01-01", "2022-07-01")]);
+        assert!((result.trades[0].return_pct - (1.01_f64.powi(11) * 0.99 - 1.0) * 100.0).abs() < 1e-9);
+
+        // Rewriting the last year leaves every earlier trade as it was.
+        let changed = half_years(&[(2020, 1.01, 0.99), (2021, 1.01, 0.99), (2022, 1.05, 0.9)]);
+        let again = run_backtest(&changed, &seasonal_config()).unwrap();
+        assert_eq!(dated(&again.trades)[0], ("2021-01-01", "2021-07-01"));
+        assert_eq!(again.trades[0].return_pct, result.trades[0].return_pct);
+        let equity = |r: &BacktestResult| r.equity_curve[..48].iter().map(|p| p.equity).collect::<Vec<f64>>();
+        assert_eq!(equity(&result), equity(&again));
+    }
+}
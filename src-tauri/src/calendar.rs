@@ -0,0 +1,433 @@
+use crate::model::ProcessedBar;
+use crate::processor::close_series;
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Calendars shipped with the app; copied next to the database on first run so users can edit them.
+const BUNDLED_CALENDARS: &str = include_str!("../calendars.json");
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Observance {
+    /// The holiday is only observed on its calendar date.
+    #[default]
+    None,
+    /// Saturday holidays move to Friday, Sunday holidays to Monday (US style).
+    NearestWeekday,
+    /// Weekend holidays move to the next weekday that is not already a holiday (UK style).
+    NextWeekday,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HolidayKind {
+    Fixed {
+        month: u32,
+        day: u32,
+        #[serde(default)]
+        observance: Observance,
+    },
+    /// `n`-th `weekday` of the month; negative `n` counts from the end (-1 = last).
+    NthWeekday { month: u32, weekday: Weekday, n: i32 },
+    /// Days relative to Western Easter Sunday.
+    Easter { offset: i64 },
+    /// One-off closure.
+    Date { date: NaiveDate },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HolidayRule {
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: HolidayKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_year: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to_year: Option<i32>,
+}
+
+fn default_weekend() -> Vec<Weekday> {
+    vec![Weekday::Sat, Weekday::Sun]
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TradingCalendar {
+    pub code: String,
+    pub name: String,
+    #[serde(default = "default_weekend")]
+    pub weekend: Vec<Weekday>,
+    #[serde(default)]
+    pub holidays: Vec<HolidayRule>,
+}
+
+/// Western Easter Sunday (anonymous Gregorian algorithm).
+fn easter_sunday(year: i32) -> Option<NaiveDate> {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32)
+}
+
+fn nth_weekday(year: i32, month: u32, weekday: Weekday, n: i32) -> Option<NaiveDate> {
+    if n > 0 {
+        NaiveDate::from_weekday_of_month_opt(year, month, weekday, n as u8)
+    } else if n < 0 {
+        let first_of_next = if month == 12 {
+            NaiveDate::from_ymd_opt(year + 1, 1, 1)?
+        } else {
+            NaiveDate::from_ymd_opt(year, month + 1, 1)?
+        };
+        let last = first_of_next - Duration::days(1);
+        let back = (7 + last.weekday().num_days_from_monday() - weekday.num_days_from_monday()) % 7;
+        let date = last - Duration::days(back as i64) - Duration::weeks((-n - 1) as i64);
+        if date.month() == month { Some(date) } else { None }
+    } else {
+        None
+    }
+}
+
+impl TradingCalendar {
+    fn is_weekend(&self, date: NaiveDate) -> bool {
+        self.weekend.contains(&date.weekday())
+    }
+
+    /// Observed holiday closures falling in `year`.
+    pub fn holidays_in_year(&self, year: i32) -> Vec<(NaiveDate, String)> {
+        let mut out: Vec<(NaiveDate, String)> = Vec::new();
+        // Weekend holidays substituted onto the next free weekday, resolved once fixed dates are known.
+        let mut substitutes: Vec<(NaiveDate, String)> = Vec::new();
+        for rule in &self.holidays {
+            if rule.from_year.is_some_and(|y| year < y) || rule.to_year.is_some_and(|y| year > y) {
+                continue;
+            }
+            let observed = match &rule.kind {
+                HolidayKind::Fixed { month, day, observance } => match NaiveDate::from_ymd_opt(year, *month, *day) {
+                    Some(date) if self.is_weekend(date) && *observance == Observance::NextWeekday => {
+                        substitutes.push((date, rule.name.clone()));
+                        None
+                    }
+                    Some(date) if *observance == Observance::NearestWeekday => match date.weekday() {
+                        Weekday::Sat => Some(date - Duration::days(1)),
+                        Weekday::Sun => Some(date + Duration::days(1)),
+                        _ => Some(date),
+                    },
+                    other => other,
+                },
+                HolidayKind::NthWeekday { month, weekday, n } => nth_weekday(year, *month, *weekday, *n),
+                HolidayKind::Easter { offset } => easter_sunday(year).map(|d| d + Duration::days(*offset)),
+                HolidayKind::Date { date } => (date.year() == year).then_some(*date),
+            };
+            // Observed dates pushed into a neighbouring year are dropped (e.g. NYSE skips a Saturday New Year).
+            if let Some(d) = observed.filter(|d| d.year() == year && !self.is_weekend(*d)) {
+                out.push((d, rule.name.clone()));
+            }
+        }
+        for (date, name) in substitutes {
+            let mut d = date;
+            // Bounded by the year end so a calendar with no trading weekday cannot loop forever.
+            while d.year() == year && (self.is_weekend(d) || out.iter().any(|(h, _)| *h == d)) {
+                d += Duration::days(1);
+            }
+            if d.year() == year {
+                out.push((d, name));
+            }
+        }
+        out.sort_by_key(|h| h.0);
+        out
+    }
+
+    pub fn is_session(&self, date: NaiveDate) -> bool {
+        !self.is_weekend(date) && !self.holidays_in_year(date.year()).iter().any(|(d, _)| *d == date)
+    }
+
+    /// Memoizing session check for scans over many dates.
+    pub fn session_checker(&self) -> impl Fn(NaiveDate) -> bool + '_ {
+        let cache: std::cell::RefCell<HashMap<i32, HashSet<NaiveDate>>> = Default::default();
+        move |date: NaiveDate| {
+            if self.is_weekend(date) {
+                return false;
+            }
+            let mut cache = cache.borrow_mut();
+            let holidays = cache
+                .entry(date.year())
+                .or_insert_with(|| self.holidays_in_year(date.year()).into_iter().map(|h| h.0).collect());
+            !holidays.contains(&date)
+        }
+    }
+}
+
+pub fn calendars_path(db_path: &Path) -> PathBuf {
+    db_path.with_file_name("calendars.json")
+}
+
+/// Seeds the user-editable calendar file from the bundled defaults.
+pub fn ensure_user_calendars(path: &Path) -> anyhow::Result<()> {
+    if !path.exists() {
+        std::fs::write(path, BUNDLED_CALENDARS)?;
+    }
+    Ok(())
+}
+
+pub fn load_calendars(path: &Path) -> anyhow::Result<Vec<TradingCalendar>> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(t) => t,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => BUNDLED_CALENDARS.to_string(),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(serde_json::from_str(&text)?)
+}
+
+pub fn find_calendar(path: &Path, code: &str) -> anyhow::Result<TradingCalendar> {
+    load_calendars(path)?
+        .into_iter()
+        .find(|c| c.code.eq_ignore_ascii_case(code))
+        .ok_or_else(|| anyhow::anyhow!("Unknown trading calendar '{}'", code))
+}
+
+/// Inserts or replaces a calendar (matched by code) in the user calendar file.
+pub fn save_calendar(path: &Path, calendar: TradingCalendar) -> anyhow::Result<()> {
+    if calendar.code.trim().is_empty() {
+        return Err(anyhow::anyhow!("Calendar code is required"));
+    }
+    if calendar.weekend.iter().collect::<HashSet<_>>().len() >= 7 {
+        return Err(anyhow::anyhow!("Calendar '{}' has no weekday left to trade on", calendar.code));
+    }
+    let mut calendars = load_calendars(path)?;
+    match calendars.iter_mut().find(|c| c.code.eq_ignore_ascii_case(&calendar.code)) {
+        Some(existing) => *existing = calendar,
+        None => calendars.push(calendar),
+    }
+    std::fs::write(path, serde_json::to_string_pretty(&calendars)?)?;
+    Ok(())
+}
+
+/// Adds "TD-no", the 1-based trading day of the year, to date-sorted rows. Rows that fall on a
+/// non-session day get `null`.
+pub fn apply_trading_day(rows: &mut [ProcessedBar], calendar: &TradingCalendar) {
+    let is_session = calendar.session_checker();
+    // Count sessions from Jan 1 up to each row's date, carrying the count forward within a year.
+    let mut cursor: Option<(NaiveDate, u32)> = None;
+    for row in rows.iter_mut() {
+        let Some(date) = row.bar.parsed_date() else { continue };
+        let (mut day, mut count) = match cursor {
+            Some((d, c)) if d.year() == date.year() && d <= date => (d, c),
+            _ => (NaiveDate::from_ymd_opt(date.year(), 1, 1).unwrap_or(date) - Duration::days(1), 0),
+        };
+        while day < date {
+            day += Duration::days(1);
+            if is_session(day) {
+                count += 1;
+            }
+        }
+        cursor = Some((day, count));
+        row.trading_day_no = if is_session(date) { Some(count as i64) } else { None };
+    }
+}
+
+#[derive(Serialize)]
+pub struct HolidayEffect {
+    pub holiday: String,
+    pub occurrences: usize,
+    /// Close-to-close % return of the last session before the holiday.
+    pub avg_pre_return: Option<f64>,
+    pub pre_win_rate: Option<f64>,
+    /// Close-to-close % return of the first session after the holiday.
+    pub avg_post_return: Option<f64>,
+    pub post_win_rate: Option<f64>,
+}
+
+fn summarize(returns: &[f64]) -> (Option<f64>, Option<f64>) {
+    if returns.is_empty() {
+        return (None, None);
+    }
+    let avg = returns.iter().sum::<f64>() / returns.len() as f64;
+    let wins = returns.iter().filter(|r| **r > 0.0).count();
+    (Some(avg), Some(wins as f64 / returns.len() as f64 * 100.0))
+}
+
+/// Average returns on the sessions either side of each calendar holiday across the stored history.
+pub fn holiday_effects(input: &[ProcessedBar], calendar: &TradingCalendar) -> Vec<HolidayEffect> {
+    let closes: Vec<(NaiveDate, f64)> = close_series(input).into_iter().filter(|c| c.1 != 0.0).collect();
+    let (Some(first), Some(last)) = (closes.first().map(|c| c.0), closes.last().map(|c| c.0)) else {
+        return vec![];
+    };
+
+    let mut names: Vec<String> = Vec::new();
+    let mut pre: HashMap<String, Vec<f64>> = HashMap::new();
+    let mut post: HashMap<String, Vec<f64>> = HashMap::new();
+    let mut occurrences: HashMap<String, usize> = HashMap::new();
+
+    for year in first.year()..=last.year() {
+        for (date, name) in calendar.holidays_in_year(year) {
+            // Index of the first bar after the holiday; the bar before it is the pre-holiday session.
+            let after = closes.partition_point(|c| c.0 <= date);
+            if after == 0 || after >= closes.len() {
+                continue;
+            }
+            if !names.contains(&name) {
+                names.push(name.clone());
+            }
+            *occurrences.entry(name.clone()).or_default() += 1;
+            let before = after - 1;
+            if before >= 1 {
+                pre.entry(name.clone())
+                    .or_default()
+                    .push((closes[before].1 / closes[before - 1].1 - 1.0) * 100.0);
+            }
+            post.entry(name)
+                .or_default()
+                .push((closes[after].1 / closes[before].1 - 1.0) * 100.0);
+        }
+    }
+
+    names
+        .into_iter()
+        .map(|name| {
+            let (avg_pre_return, pre_win_rate) = summarize(pre.get(&name).map(|v| v.as_slice()).unwrap_or(&[]));
+            let (avg_post_return, post_win_rate) = summarize(post.get(&name).map(|v| v.as_slice()).unwrap_or(&[]));
+            HolidayEffect {
+                occurrences: occurrences.get(&name).copied().unwrap_or(0),
+                holiday: name,
+                avg_pre_return,
+                pre_win_rate,
+                avg_post_return,
+                post_win_rate,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::test_bar;
+    use serde_json::json;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn calendar(holidays: serde_json::Value) -> TradingCalendar {
+        serde_json::from_value(json!({ "code": "T", "name": "Test", "holidays": holidays })).unwrap()
+    }
+
+    fn dates(calendar: &TradingCalendar, year: i32) -> Vec<NaiveDate> {
+        calendar.holidays_in_year(year).into_iter().map(|h| h.0).collect()
+    }
+
+    #[test]
+    fn nearest_weekday_moves_weekend_holidays_within_the_year() {
+        let cal = calendar(json!([
+            { "name": "Independence Day", "type": "fixed", "month": 7, "day": 4, "observance": "nearest_weekday" },
+            { "name": "New Year", "type": "fixed", "month": 1, "day": 1, "observance": "nearest_weekday" },
+        ]));
+        // 2021-07-04 is a Sunday; 2022-01-01 is a Saturday whose Friday falls in 2021 and is dropped.
+        assert_eq!(dates(&cal, 2021), vec![date(2021, 1, 1), date(2021, 7, 5)]);
+        assert_eq!(dates(&cal, 2022), vec![date(2022, 7, 4)]);
+    }
+
+    #[test]
+    fn next_weekday_skips_holidays_already_taken() {
+        let cal = calendar(json!([
+            { "name": "Christmas", "type": "fixed", "month": 12, "day": 25, "observance": "next_weekday" },
+            { "name": "Boxing Day", "type": "fixed", "month": 12, "day": 26, "observance": "next_weekday" },
+        ]));
+        // 2021-12-25 is a Saturday and the 26th a Sunday.
+        assert_eq!(dates(&cal, 2021), vec![date(2021, 12, 27), date(2021, 12, 28)]);
+        assert_eq!(dates(&cal, 2023), vec![date(2023, 12, 25), date(2023, 12, 26)]);
+    }
+
+    #[test]
+    fn weekday_and_easter_rules() {
+        let cal = calendar(json!([
+            { "name": "Thanksgiving", "type": "nth_weekday", "month": 11, "weekday": "Thu", "n": 4 },
+            { "name": "Memorial Day", "type": "nth_weekday", "month": 5, "weekday": "Mon", "n": -1 },
+            { "name": "Good Friday", "type": "easter", "offset": -2 },
+            { "name": "Easter Monday", "type": "easter", "offset": 1 },
+        ]));
+        assert_eq!(
+            dates(&cal, 2024),
+            vec![date(2024, 3, 29), date(2024, 4, 1), date(2024, 5, 27), date(2024, 11, 28)]
+        );
+        assert_eq!(nth_weekday(2024, 12, Weekday::Tue, -1), Some(date(2024, 12, 31)));
+        assert_eq!(nth_weekday(2024, 2, Weekday::Thu, 5), Some(date(2024, 2, 29)));
+        assert_eq!(nth_weekday(2023, 2, Weekday::Thu, 5), None);
+        assert_eq!(easter_sunday(2025), Some(date(2025, 4, 20)));
+    }
+
+    #[test]
+    fn rules_apply_only_within_their_years() {
+        let cal = calendar(json!([
+            { "name": "Juneteenth", "type": "fixed", "month": 6, "day": 19, "observance": "nearest_weekday", "from_year": 2022 },
+            { "name": "Old Closure", "type": "fixed", "month": 3, "day": 2, "to_year": 2020 },
+            { "name": "Funeral", "type": "date", "date": "2023-01-09" },
+        ]));
+        assert_eq!(dates(&cal, 2020), vec![date(2020, 3, 2)]);
+        assert_eq!(dates(&cal, 2021), vec![]);
+        assert_eq!(dates(&cal, 2023), vec![date(2023, 1, 9), date(2023, 6, 19)]);
+    }
+
+    #[test]
+    fn sessions_exclude_weekends_and_holidays() {
+        let cal = calendar(json!([{ "name": "Good Friday", "type": "easter", "offset": -2 }]));
+        let check = cal.session_checker();
+        for (d, expected) in [(date(2024, 3, 28), true), (date(2024, 3, 29), false), (date(2024, 3, 30), false), (date(2024, 4, 1), true)] {
+            assert_eq!(cal.is_session(d), expected, "{}", d);
+            assert_eq!(check(d), expected, "{}", d);
+        }
+    }
+
+    #[test]
+    fn trading_day_counts_sessions_from_the_start_of_the_year() {
+        let cal = calendar(json!([{ "name": "New Year", "type": "fixed", "month": 1, "day": 1 }]));
+        let mut rows: Vec<ProcessedBar> = ["2024-01-02", "2024-01-06", "2024-01-08", "2025-01-02"]
+            .iter()
+            .map(|d| test_bar(d, 1.0))
+            .collect();
+        apply_trading_day(&mut rows, &cal);
+        let numbers: Vec<Option<i64>> = rows.iter().map(|r| r.trading_day_no).collect();
+        assert_eq!(numbers, vec![Some(1), None, Some(5), Some(1)]);
+    }
+
+    #[test]
+    fn a_calendar_without_trading_weekdays_is_rejected() {
+        let mut cal = calendar(json!([{ "name": "Christmas", "type": "fixed", "month": 12, "day": 25, "observance": "next_weekday" }]));
+        cal.weekend = vec![Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri, Weekday::Sat, Weekday::Sun];
+        // Substitution gives up at the year end instead of looping.
+        assert_eq!(dates(&cal, 2021), vec![]);
+        let path = std::env::temp_dir().join(format!("seasonality-calendars-{}.json", std::process::id()));
+        let error = save_calendar(&path, cal).unwrap_err();
+        assert!(error.to_string().contains("no weekday"), "{}", error);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn holiday_returns_use_the_last_bar_of_each_day() {
+        let cal = calendar(json!([{ "name": "Closure", "type": "date", "date": "2024-01-03" }]));
+        let input = vec![
+            test_bar("2024-01-01", 100.0),
+            test_bar("2024-01-02T10:00:00", 90.0),
+            test_bar("2024-01-02T16:00:00", 110.0),
+            test_bar("2024-01-04T10:00:00", 99.0),
+            test_bar("2024-01-04T16:00:00", 121.0),
+        ];
+        let effects = holiday_effects(&input, &cal);
+        assert_eq!(effects.len(), 1);
+        assert_eq!(effects[0].occurrences, 1);
+        assert!((effects[0].avg_pre_return.unwrap() - 10.0).abs() < 1e-9);
+        assert!((effects[0].avg_post_return.unwrap() - 10.0).abs() < 1e-9);
+    }
+}
@@ -1,9 +1,23 @@
+use crate::adjustments::{self, CorporateAction};
+use crate::backtest;
+use crate::calendar::{self, TradingCalendar};
+use crate::correlation::{self, CorrelationMatrix};
+use crate::cycles::YearFilter;
 use crate::db::{self, DbState};
-use crate::processor;
+use crate::events::{self, EventSource};
+use crate::gaps::{self, FillPolicy};
+use crate::indicators::{self, IndicatorSpec};
+use crate::model::{self, OhlcBar, ProcessedBar};
+use crate::processor::{self, ProcessingOptions};
+use crate::risk;
+use crate::screener;
+use crate::rolling::{self, ContractSpec, RollAdjustment, RollEvent, RollRule};
+use crate::synthetic::{self, SyntheticFormula};
 use calamine::{Data, Reader, Xlsx};
-use rusqlite::{params, Connection, Row};
+use rusqlite::{params, Connection};
 use serde::Serialize;
 use serde_json::{Map, Value};
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 use tauri::State;
@@ -51,6 +65,26 @@ fn ok_msg(msg: String) -> ApiResult<Value> {
     }
 }
 
+/// Success, noting dependent assets that could not be rebuilt (see [`refresh_dependents`]).
+fn ok_with_warnings<T>(data: T, warnings: Vec<String>) -> ApiResult<T> {
+    ApiResult { message: dependents_message(&warnings), ..ok(data) }
+}
+
+fn dependents_message(warnings: &[String]) -> Option<String> {
+    if warnings.is_empty() {
+        None
+    } else {
+        Some(format!("Dependent assets were not rebuilt: {}", warnings.join("; ")))
+    }
+}
+
+fn with_dependents_note(message: String, warnings: &[String]) -> String {
+    match dependents_message(warnings) {
+        Some(note) => format!("{}. {}", message, note),
+        None => message,
+    }
+}
+
 fn err<T>(e: impl ToString) -> ApiResult<T> {
     ApiResult {
         success: false,
@@ -69,44 +103,29 @@ fn conn(state: &DbState) -> anyhow::Result<Connection> {
     Ok(Connection::open(&state.db_path)?)
 }
 
-fn row_to_map(row: &Row) -> rusqlite::Result<Map<String, Value>> {
-    let mut m = Map::new();
-    m.insert("id".to_string(), Value::Number(row.get::<_, i64>("id")?.into()));
-
-    let date: Option<String> = row.get("Date")?;
-    m.insert("Date".to_string(), date.map(Value::String).unwrap_or(Value::Null));
-
-    for key in ["Open", "High", "Low", "Close", "normalized", "Average_Norm", "True_Seasonal"] {
-        let v: Option<f64> = row.get(key)?;
-        m.insert(
-            key.to_string(),
-            v.and_then(serde_json::Number::from_f64).map(Value::Number).unwrap_or(Value::Null),
-        );
-    }
-
-    let pct: Option<f64> = row.get("%change")?;
-    m.insert(
-        "%change".to_string(),
-        pct.and_then(serde_json::Number::from_f64).map(Value::Number).unwrap_or(Value::Null),
-    );
-
-    let m_no: Option<i64> = row.get("M-no")?;
-    m.insert(
-        "M-no".to_string(),
-        m_no.map(|n| Value::Number(n.into())).unwrap_or(Value::Null),
-    );
+fn query_asset_bars(conn: &Connection, asset_name: &str) -> anyhow::Result<Vec<ProcessedBar>> {
+    let mut stmt = conn.prepare("SELECT * FROM asset_data WHERE asset = ?1 ORDER BY Date")?;
+    let bars = stmt
+        .query_map(params![asset_name], db::read_bar)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(bars)
+}
 
-    let asset: Option<String> = row.get("asset")?;
-    if let Some(a) = asset {
-        m.insert("asset".to_string(), Value::String(a));
-    }
+/// Source bars with their row ids, for recomputing an asset after an edit.
+fn query_source_bars(conn: &Connection, asset_name: &str) -> anyhow::Result<Vec<(i64, OhlcBar)>> {
+    let mut stmt = conn.prepare("SELECT * FROM asset_data WHERE asset = ?1 ORDER BY Date")?;
+    let bars = stmt
+        .query_map(params![asset_name], db::read_source_bar)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(bars)
+}
 
-    let processed_date: Option<String> = row.get("processed_date")?;
-    if let Some(p) = processed_date {
-        m.insert("processed_date".to_string(), Value::String(p));
+/// The trading calendar assigned to an asset, if any.
+fn asset_calendar(conn: &Connection, state: &DbState, asset_name: &str) -> anyhow::Result<Option<TradingCalendar>> {
+    match db::get_asset_setting(conn, asset_name, "calendar")? {
+        Some(code) => Ok(Some(calendar::find_calendar(&calendar::calendars_path(&state.db_path), &code)?)),
+        None => Ok(None),
     }
-
-    Ok(m)
 }
 
 #[tauri::command(rename = "show_open_dialog")]
@@ -147,7 +166,7 @@ pub async fn show_save_dialog(default_filename: String) -> Result<DialogSaveResu
     }
 }
 
-fn parse_xlsx_bytes(buffer: Vec<u8>) -> ApiResult<Vec<Map<String, Value>>> {
+fn parse_xlsx_bytes(buffer: Vec<u8>) -> ApiResult<Vec<OhlcBar>> {
     // Parse first sheet as "sheet_to_json"-like output.
     let cursor = std::io::Cursor::new(buffer);
     let mut workbook: Xlsx<_> = match Xlsx::new(cursor) {
@@ -178,7 +197,7 @@ fn parse_xlsx_bytes(buffer: Vec<u8>) -> ApiResult<Vec<Map<String, Value>>> {
         .map(|s| s.trim().to_string())
         .collect();
 
-    let mut out: Vec<Map<String, Value>> = Vec::new();
+    let mut out: Vec<OhlcBar> = Vec::new();
     for r in rows_iter {
         let mut m = Map::new();
         for (idx, cell) in r.iter().enumerate() {
@@ -200,7 +219,10 @@ fn parse_xlsx_bytes(buffer: Vec<u8>) -> ApiResult<Vec<Map<String, Value>>> {
             m.insert(key, v);
         }
         if !m.is_empty() {
-            out.push(m);
+            match OhlcBar::from_map(&m) {
+                Ok(bar) => out.push(bar),
+                Err(e) => return err(e),
+            }
         }
     }
 
@@ -208,7 +230,7 @@ fn parse_xlsx_bytes(buffer: Vec<u8>) -> ApiResult<Vec<Map<String, Value>>> {
 }
 
 #[tauri::command(rename = "read_excel_file")]
-pub fn read_excel_file(file_path: String) -> ApiResult<Vec<Map<String, Value>>> {
+pub fn read_excel_file(file_path: String) -> ApiResult<Vec<OhlcBar>> {
     match fs::read(file_path) {
         Ok(bytes) => parse_xlsx_bytes(bytes),
         Err(e) => err(e),
@@ -216,69 +238,88 @@ pub fn read_excel_file(file_path: String) -> ApiResult<Vec<Map<String, Value>>>
 }
 
 #[tauri::command(rename = "read_excel_buffer")]
-pub fn read_excel_buffer(buffer: Vec<u8>) -> ApiResult<Vec<Map<String, Value>>> {
+pub fn read_excel_buffer(buffer: Vec<u8>) -> ApiResult<Vec<OhlcBar>> {
     parse_xlsx_bytes(buffer)
 }
 
 #[tauri::command(rename = "process_data")]
-pub fn process_data(data: Vec<Map<String, Value>>, replace_nan: bool) -> ApiResult<Vec<Map<String, Value>>> {
-    let processed = processor::calculate_derived_columns(&data, replace_nan);
+pub fn process_data(
+    state: State<'_, DbState>,
+    data: Vec<OhlcBar>,
+    replace_nan: bool,
+    indicators: Option<Vec<IndicatorSpec>>,
+    fill_policy: Option<FillPolicy>,
+    calendar: Option<String>,
+    options: Option<ProcessingOptions>,
+) -> ApiResult<Vec<ProcessedBar>> {
+    let cal = match calendar.map(|code| calendar::find_calendar(&calendar::calendars_path(&state.db_path), &code)) {
+        Some(Ok(cal)) => Some(cal),
+        Some(Err(e)) => return err(e),
+        None => None,
+    };
+
+    let mut options = options.unwrap_or_default();
+    if let Some(policy) = fill_policy {
+        options.fill_policy = policy;
+    }
+    let data = match &cal {
+        Some(cal) => gaps::fill_gaps(&data, options.fill_policy, &cal.session_checker()),
+        None => gaps::fill_gaps(&data, options.fill_policy, &gaps::is_weekday),
+    };
+    let mut processed = processor::calculate_derived_columns(&data, replace_nan, &options);
+    indicators::apply_indicators(&mut processed, &indicators.unwrap_or_default());
+    if let Some(cal) = &cal {
+        calendar::apply_trading_day(&mut processed, cal);
+    }
     ok(processed)
 }
 
 #[tauri::command(rename = "save_to_database")]
-pub fn save_to_database(state: State<'_, DbState>, data: Vec<Map<String, Value>>, asset_name: String) -> ApiResult<Value> {
+pub fn save_to_database(
+    state: State<'_, DbState>,
+    mut data: Vec<ProcessedBar>,
+    asset_name: String,
+    indicators: Option<Vec<IndicatorSpec>>,
+    options: Option<Map<String, Value>>,
+) -> ApiResult<Value> {
     let result = with_db_lock(&state, || {
         db::ensure_schema(&state.db_path)?;
         let mut conn = conn(&state)?;
         let tx = conn.transaction()?;
 
-        let stmt_sql = r#"
-          INSERT INTO asset_data
-            (Date, Open, High, Low, Close, "%change", "M-no", normalized, Average_Norm, True_Seasonal, asset, processed_date)
-          VALUES
-            (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
-        "#;
-        {
-            let mut stmt = tx.prepare(stmt_sql)?;
-
-        let now = chrono::Utc::now().to_rfc3339();
-
-        for row in &data {
-            let date = row.get("Date").and_then(|v| v.as_str()).unwrap_or("");
-            let open = row.get("Open").and_then(|v| v.as_f64()).unwrap_or(0.0);
-            let high = row.get("High").and_then(|v| v.as_f64()).unwrap_or(0.0);
-            let low = row.get("Low").and_then(|v| v.as_f64()).unwrap_or(0.0);
-            let close = row.get("Close").and_then(|v| v.as_f64()).unwrap_or(0.0);
-            let pct = row.get("%change").and_then(|v| v.as_f64()).unwrap_or(0.0);
-            let m_no = row.get("M-no").and_then(|v| v.as_i64()).unwrap_or(0);
-            let norm = row.get("normalized").and_then(|v| v.as_f64()).unwrap_or(0.0);
-            let avg_norm = row.get("Average_Norm").and_then(|v| v.as_f64()).unwrap_or(0.0);
-            let true_seasonal = row.get("True_Seasonal").and_then(|v| v.as_f64()).unwrap_or(0.0);
-
-            stmt.execute(params![
-                date,
-                open,
-                high,
-                low,
-                close,
-                pct,
-                m_no,
-                norm,
-                avg_norm,
-                true_seasonal,
-                &asset_name,
-                now
-            ])?;
-        }
+        let previous_specs = db::load_indicator_specs(&tx, &asset_name)?;
+        let specs = match indicators {
+            Some(specs) => {
+                db::save_indicator_specs(&tx, &asset_name, &specs)?;
+                specs
+            }
+            None => previous_specs.clone(),
+        };
+        // Stored rows carry exactly the asset's indicator set
+        indicators::remove_indicators(&mut data, &previous_specs);
+        indicators::apply_indicators(&mut data, &specs);
+        // The form only sends the options it shows; the asset's other settings are kept
+        if let Some(changes) = options {
+            let merged = db::load_processing_options(&tx, &asset_name)?.merged(changes)?;
+            db::save_processing_options(&tx, &asset_name, &merged)?;
         }
 
+        db::insert_bars(&tx, &data, &asset_name, &indicators::column_names(&specs))?;
+
         tx.commit()?;
-        Ok::<_, anyhow::Error>(data.len())
+        // Stored splits and dividends apply to new bars too, and rows saved earlier need the new
+        // indicator set; either way the whole asset is recomputed
+        let warnings = if specs == previous_specs && db::load_corporate_actions(&conn, &asset_name)?.is_empty() {
+            refresh_dependents(&mut conn, &state, &asset_name)
+        } else {
+            let base: Vec<OhlcBar> = query_source_bars(&conn, &asset_name)?.into_iter().map(|(_, bar)| bar).collect();
+            reprocess_asset(&mut conn, &state, &asset_name, &base)?.1
+        };
+        Ok::<_, anyhow::Error>((data.len(), warnings))
     });
 
     match result {
-        Ok(count) => ok_msg(format!("Successfully saved {} rows for '{}'", count, asset_name)),
+        Ok((count, warnings)) => ok_msg(with_dependents_note(format!("Successfully saved {} rows for '{}'", count, asset_name), &warnings)),
         Err(e) => err(e),
     }
 }
@@ -302,15 +343,11 @@ pub fn get_assets(state: State<'_, DbState>) -> ApiResult<Vec<String>> {
 }
 
 #[tauri::command(rename = "get_asset_data")]
-pub fn get_asset_data(state: State<'_, DbState>, asset_name: String) -> ApiResult<Vec<Map<String, Value>>> {
+pub fn get_asset_data(state: State<'_, DbState>, asset_name: String) -> ApiResult<Vec<ProcessedBar>> {
     let result = with_db_lock(&state, || {
         db::ensure_schema(&state.db_path)?;
         let conn = conn(&state)?;
-        let mut stmt = conn.prepare("SELECT * FROM asset_data WHERE asset = ?1 ORDER BY Date")?;
-        let mapped = stmt
-            .query_map(params![asset_name], |row| row_to_map(row))?
-            .collect::<Result<Vec<_>, _>>()?;
-        Ok::<_, anyhow::Error>(mapped)
+        query_asset_bars(&conn, &asset_name)
     });
 
     match result {
@@ -377,37 +414,167 @@ pub fn get_asset_date_range(state: State<'_, DbState>, asset_name: String) -> Ap
     }
 }
 
-#[tauri::command(rename = "add_row")]
-pub fn add_row(state: State<'_, DbState>, asset_name: String, new_row: Map<String, Value>) -> ApiResult<Vec<Map<String, Value>>> {
-    // Fetch base rows, append, recalc, replace asset data
+#[tauri::command(rename = "find_analog_years")]
+pub fn find_analog_years(state: State<'_, DbState>, asset_name: String, top_n: Option<usize>) -> ApiResult<processor::AnalogYearsResult> {
     let result = with_db_lock(&state, || {
         db::ensure_schema(&state.db_path)?;
-        let mut conn = conn(&state)?;
+        let conn = conn(&state)?;
+        let rows = query_asset_bars(&conn, &asset_name)?;
+        Ok::<_, anyhow::Error>(processor::find_analog_years(&rows, top_n.unwrap_or(5)))
+    });
 
-        let mut base: Vec<Map<String, Value>> = {
-            let mut stmt = conn.prepare("SELECT Date, Open, High, Low, Close FROM asset_data WHERE asset = ?1 ORDER BY Date")?;
-            let rows = stmt.query_map(params![&asset_name], |r| {
-                    let mut m = Map::new();
-                    let date: Option<String> = r.get(0)?;
-                    m.insert("Date".to_string(), date.map(Value::String).unwrap_or(Value::Null));
-                    for (i, key) in [(1, "Open"), (2, "High"), (3, "Low"), (4, "Close")] {
-                        let v: Option<f64> = r.get(i)?;
-                        m.insert(key.to_string(), v.and_then(serde_json::Number::from_f64).map(Value::Number).unwrap_or(Value::Null));
-                    }
-                    Ok(m)
-                })?;
-            rows.collect::<Result<Vec<_>, _>>()?
+    match result {
+        Ok(v) => ok(v),
+        Err(e) => err(e),
+    }
+}
+
+/// Projects the rest of the current year from the latest close. Lookbacks are numbers of past years (0 = all).
+#[tauri::command(rename = "get_seasonal_forecast")]
+pub fn get_seasonal_forecast(
+    state: State<'_, DbState>,
+    asset_name: String,
+    lookbacks: Option<Vec<usize>>,
+    percentiles: Option<Vec<f64>>,
+) -> ApiResult<processor::SeasonalForecast> {
+    let result = with_db_lock(&state, || {
+        db::ensure_schema(&state.db_path)?;
+        let conn = conn(&state)?;
+        let rows = query_asset_bars(&conn, &asset_name)?;
+        processor::seasonal_forecast(
+            &rows,
+            &lookbacks.unwrap_or_else(|| vec![5, 10, 15]),
+            &percentiles.unwrap_or_else(|| vec![10.0, 25.0, 75.0, 90.0]),
+        )
+        .ok_or_else(|| anyhow::anyhow!("No price history for '{}'", asset_name))
+    });
+
+    match result {
+        Ok(v) => ok(v),
+        Err(e) => err(e),
+    }
+}
+
+/// Average seasonal curve with bootstrap confidence bands (resampling whole years).
+#[tauri::command(rename = "get_seasonal_bands")]
+pub fn get_seasonal_bands(
+    state: State<'_, DbState>,
+    asset_name: String,
+    basis: Option<processor::CurveBasis>,
+    resamples: Option<usize>,
+    confidence: Option<f64>,
+    seed: Option<u64>,
+) -> ApiResult<processor::SeasonalBands> {
+    let result = with_db_lock(&state, || {
+        db::ensure_schema(&state.db_path)?;
+        let conn = conn(&state)?;
+        let rows = query_asset_bars(&conn, &asset_name)?;
+        Ok::<_, anyhow::Error>(processor::seasonal_bootstrap(
+            &rows,
+            basis.unwrap_or_default(),
+            resamples.unwrap_or(1000),
+            confidence.unwrap_or(0.9),
+            seed.unwrap_or(42),
+        ))
+    });
+
+    match result {
+        Ok(v) => ok(v),
+        Err(e) => err(e),
+    }
+}
+
+/// Seasonal composites from only the years matching a filter (election years, decennial digit, after a down year, ...).
+#[tauri::command(rename = "get_filtered_seasonality")]
+pub fn get_filtered_seasonality(
+    state: State<'_, DbState>,
+    asset_name: String,
+    filter: YearFilter,
+    basis: Option<processor::CurveBasis>,
+) -> ApiResult<processor::FilteredSeasonality> {
+    let result = with_db_lock(&state, || {
+        db::ensure_schema(&state.db_path)?;
+        let conn = conn(&state)?;
+        let rows = query_asset_bars(&conn, &asset_name)?;
+        Ok::<_, anyhow::Error>(processor::filtered_seasonality(&rows, &filter, basis.unwrap_or_default()))
+    });
+
+    match result {
+        Ok(v) => ok(v),
+        Err(e) => err(e),
+    }
+}
+
+/// Ranks stored assets (all of them unless `assets` is given) over a forward calendar window.
+#[tauri::command(rename = "run_seasonal_screener")]
+pub fn run_seasonal_screener(
+    state: State<'_, DbState>,
+    config: screener::ScreenerConfig,
+    assets: Option<Vec<String>>,
+) -> ApiResult<screener::ScreenerResult> {
+    let result = with_db_lock(&state, || {
+        db::ensure_schema(&state.db_path)?;
+        let conn = conn(&state)?;
+        let names = match assets {
+            Some(names) => names,
+            None => {
+                let mut stmt = conn.prepare("SELECT DISTINCT asset FROM asset_data ORDER BY asset")?;
+                let rows = stmt.query_map([], |r| r.get::<_, String>(0))?.collect::<Result<Vec<_>, _>>()?;
+                rows
+            }
         };
+        let mut inputs = Vec::with_capacity(names.len());
+        for name in names {
+            let rows = query_asset_bars(&conn, &name)?;
+            inputs.push((name, rows));
+        }
+        screener::screen(&inputs, &config)
+    });
 
-        base.push(new_row);
+    match result {
+        Ok(v) => ok(v),
+        Err(e) => err(e),
+    }
+}
+
+/// Searches start-date/holding-period combinations for the asset's historically strongest windows.
+#[tauri::command(rename = "find_best_windows")]
+pub fn find_best_windows(state: State<'_, DbState>, asset_name: String, search: screener::WindowSearch) -> ApiResult<screener::WindowSearchResult> {
+    let result = with_db_lock(&state, || {
+        db::ensure_schema(&state.db_path)?;
+        let conn = conn(&state)?;
+        let rows = query_asset_bars(&conn, &asset_name)?;
+        screener::find_best_windows(&rows, &search)
+    });
+
+    match result {
+        Ok(v) => ok(v),
+        Err(e) => err(e),
+    }
+}
 
-        let processed = processor::calculate_derived_columns(&base, true);
+#[tauri::command(rename = "run_backtest")]
+pub fn run_backtest(state: State<'_, DbState>, asset_name: String, config: backtest::BacktestConfig) -> ApiResult<backtest::BacktestResult> {
+    let result = with_db_lock(&state, || {
+        db::ensure_schema(&state.db_path)?;
+        let conn = conn(&state)?;
+        let rows = query_asset_bars(&conn, &asset_name)?;
+        backtest::run_backtest(&rows, &config)
+    });
 
-        // Replace asset
-        conn.execute("DELETE FROM asset_data WHERE asset = ?1", params![&asset_name])?;
-        insert_processed(&mut conn, &processed, &asset_name)?;
+    match result {
+        Ok(v) => ok(v),
+        Err(e) => err(e),
+    }
+}
 
-        Ok::<_, anyhow::Error>(processed)
+#[tauri::command(rename = "get_volume_seasonality")]
+pub fn get_volume_seasonality(state: State<'_, DbState>, asset_name: String) -> ApiResult<Vec<processor::MonthVolumeStats>> {
+    let result = with_db_lock(&state, || {
+        db::ensure_schema(&state.db_path)?;
+        let conn = conn(&state)?;
+        let rows = query_asset_bars(&conn, &asset_name)?;
+        Ok::<_, anyhow::Error>(processor::volume_seasonality(&rows))
     });
 
     match result {
@@ -416,45 +583,482 @@ pub fn add_row(state: State<'_, DbState>, asset_name: String, new_row: Map<Strin
     }
 }
 
-#[tauri::command(rename = "update_row")]
-pub fn update_row(state: State<'_, DbState>, asset_name: String, row_id: Value, updated_row: Map<String, Value>) -> ApiResult<Vec<Map<String, Value>>> {
-    let row_id_str = row_id.to_string();
+#[tauri::command(rename = "get_risk_seasonality")]
+pub fn get_risk_seasonality(state: State<'_, DbState>, asset_name: String) -> ApiResult<Vec<risk::MonthRiskStats>> {
+    let result = with_db_lock(&state, || {
+        db::ensure_schema(&state.db_path)?;
+        let conn = conn(&state)?;
+        let rows = query_asset_bars(&conn, &asset_name)?;
+        Ok::<_, anyhow::Error>(risk::monthly_risk(&rows))
+    });
+
+    match result {
+        Ok(v) => ok(v),
+        Err(e) => err(e),
+    }
+}
 
+/// Writes a per-month seasonal summary (direction and risk) for a stored asset to xlsx.
+#[tauri::command(rename = "export_seasonal_summary")]
+pub fn export_seasonal_summary(state: State<'_, DbState>, asset_name: String, file_path: String) -> ApiResult<Value> {
+    use rust_xlsxwriter::Workbook;
+
+    let result = with_db_lock(&state, || {
+        db::ensure_schema(&state.db_path)?;
+        let conn = conn(&state)?;
+        let stats = risk::monthly_risk(&query_asset_bars(&conn, &asset_name)?);
+
+        let mut workbook = Workbook::new();
+        let sheet = workbook.add_worksheet();
+        sheet.set_name("Seasonal Summary")?;
+        let headers = [
+            "Month",
+            "Years",
+            "Avg Return %",
+            "Win Rate %",
+            "Avg Max Drawdown %",
+            "Worst Drawdown %",
+            "Avg ATR",
+            "Avg ATR %",
+            "Realized Vol %",
+        ];
+        for (col, h) in headers.iter().enumerate() {
+            sheet.write_string(0, col as u16, *h)?;
+        }
+        for (i, m) in stats.iter().enumerate() {
+            let row = i as u32 + 1;
+            sheet.write_number(row, 0, m.month as f64)?;
+            sheet.write_number(row, 1, m.years as f64)?;
+            let values = [
+                m.avg_return_pct,
+                m.win_rate,
+                m.avg_max_drawdown_pct,
+                m.worst_drawdown_pct,
+                m.avg_atr,
+                m.avg_atr_pct,
+                m.realized_vol_pct,
+            ];
+            for (col, v) in values.iter().enumerate() {
+                if let Some(v) = v {
+                    sheet.write_number(row, col as u16 + 2, *v)?;
+                }
+            }
+        }
+        workbook.save(&file_path)?;
+        Ok::<_, anyhow::Error>(())
+    });
+
+    match result {
+        Ok(_) => ApiResult { success: true, data: None, message: None, error: None },
+        Err(e) => err(e),
+    }
+}
+
+/// Resamples a stored asset and reprocesses the result; when `save_as` is given the series is
+/// stored as a derived asset under that name (replacing any previous rows for it).
+#[tauri::command(rename = "resample_asset")]
+pub fn resample_asset(
+    state: State<'_, DbState>,
+    asset_name: String,
+    period: processor::ResamplePeriod,
+    save_as: Option<String>,
+) -> ApiResult<Vec<ProcessedBar>> {
     let result = with_db_lock(&state, || {
         db::ensure_schema(&state.db_path)?;
         let mut conn = conn(&state)?;
+        let rows = query_asset_bars(&conn, &asset_name)?;
+        let options = db::load_processing_options(&conn, &asset_name)?;
+        let mut processed = processor::calculate_derived_columns(&processor::resample(&rows, period), false, &options);
+        let mut warnings = Vec::new();
+
+        if let Some(target) = save_as.map(|s| s.trim().to_string()).filter(|s| !s.is_empty()) {
+            if target == asset_name {
+                return Err(anyhow::anyhow!("Resampled series cannot replace its source asset"));
+            }
+            let specs = db::load_indicator_specs(&conn, &target)?;
+            indicators::apply_indicators(&mut processed, &specs);
+            let tx = conn.transaction()?;
+            tx.execute("DELETE FROM asset_data WHERE asset = ?1", params![&target])?;
+            db::insert_bars(&tx, &processed, &target, &indicators::column_names(&specs))?;
+            // The target keeps the options it was processed with, so later edits reprocess it the same way.
+            db::save_processing_options(&tx, &target, &options)?;
+            tx.commit()?;
+            warnings = refresh_dependents(&mut conn, &state, &target);
+        }
 
-        let mut base: Vec<(i64, Map<String, Value>)> = {
-            let mut stmt = conn.prepare("SELECT id, Date, Open, High, Low, Close FROM asset_data WHERE asset = ?1 ORDER BY Date")?;
-            let rows = stmt.query_map(params![&asset_name], |r| {
-                    let id: i64 = r.get(0)?;
-                    let mut m = Map::new();
-                    let date: Option<String> = r.get(1)?;
-                    m.insert("Date".to_string(), date.map(Value::String).unwrap_or(Value::Null));
-                    for (i, key) in [(2, "Open"), (3, "High"), (4, "Low"), (5, "Close")] {
-                        let v: Option<f64> = r.get(i)?;
-                        m.insert(key.to_string(), v.and_then(serde_json::Number::from_f64).map(Value::Number).unwrap_or(Value::Null));
-                    }
-                    Ok((id, m))
-                })?;
-            rows.collect::<Result<Vec<_>, _>>()?
+        Ok::<_, anyhow::Error>((processed, warnings))
+    });
+
+    match result {
+        Ok((v, warnings)) => ok_with_warnings(v, warnings),
+        Err(e) => err(e),
+    }
+}
+
+#[tauri::command(rename = "get_intraday_seasonality")]
+pub fn get_intraday_seasonality(
+    state: State<'_, DbState>,
+    asset_name: String,
+    sessions: Option<Vec<processor::SessionSpec>>,
+) -> ApiResult<processor::IntradaySeasonality> {
+    let result = with_db_lock(&state, || {
+        db::ensure_schema(&state.db_path)?;
+        let conn = conn(&state)?;
+        let rows = query_asset_bars(&conn, &asset_name)?;
+        let sessions = sessions.unwrap_or_else(processor::default_sessions);
+        Ok::<_, anyhow::Error>(processor::intraday_seasonality(&rows, &sessions))
+    });
+
+    match result {
+        Ok(v) => ok(v),
+        Err(e) => err(e),
+    }
+}
+
+#[tauri::command(rename = "detect_gaps")]
+pub fn detect_gaps(state: State<'_, DbState>, asset_name: String) -> ApiResult<gaps::GapReport> {
+    let result = with_db_lock(&state, || {
+        db::ensure_schema(&state.db_path)?;
+        let conn = conn(&state)?;
+        let rows = query_asset_bars(&conn, &asset_name)?;
+        let report = match asset_calendar(&conn, &state, &asset_name)? {
+            Some(cal) => gaps::detect_gaps(&rows, &cal.session_checker()),
+            None => gaps::detect_gaps(&rows, &gaps::is_weekday),
         };
+        Ok::<_, anyhow::Error>(report)
+    });
 
-        let idx = base.iter().position(|(id, _)| id.to_string() == row_id_str.trim_matches('"'));
-        let Some(i) = idx else { return Err(anyhow::anyhow!("Row not found")); };
+    match result {
+        Ok(v) => ok(v),
+        Err(e) => err(e),
+    }
+}
+
+#[tauri::command(rename = "get_calendars")]
+pub fn get_calendars(state: State<'_, DbState>) -> ApiResult<Vec<TradingCalendar>> {
+    match calendar::load_calendars(&calendar::calendars_path(&state.db_path)) {
+        Ok(v) => ok(v),
+        Err(e) => err(e),
+    }
+}
+
+#[tauri::command(rename = "save_calendar")]
+pub fn save_calendar(state: State<'_, DbState>, calendar: TradingCalendar) -> ApiResult<Value> {
+    let code = calendar.code.clone();
+    match calendar::save_calendar(&calendar::calendars_path(&state.db_path), calendar) {
+        Ok(_) => ok_msg(format!("Saved trading calendar '{}'", code)),
+        Err(e) => err(e),
+    }
+}
+
+/// Assigns a trading calendar to an asset (or clears it with `None`) and refreshes its "TD-no" column.
+#[tauri::command(rename = "set_asset_calendar")]
+pub fn set_asset_calendar(state: State<'_, DbState>, asset_name: String, calendar: Option<String>) -> ApiResult<Value> {
+    let result = with_db_lock(&state, || {
+        db::ensure_schema(&state.db_path)?;
+        let mut conn = conn(&state)?;
+        let cal = match &calendar {
+            Some(code) => Some(calendar::find_calendar(&calendar::calendars_path(&state.db_path), code)?),
+            None => None,
+        };
+
+        let mut rows = query_asset_bars(&conn, &asset_name)?;
+        for row in &mut rows {
+            row.trading_day_no = None;
+        }
+        if let Some(cal) = &cal {
+            calendar::apply_trading_day(&mut rows, cal);
+        }
+
+        let tx = conn.transaction()?;
+        db::set_asset_setting(&tx, &asset_name, "calendar", cal.as_ref().map(|c| c.code.as_str()))?;
+        {
+            let mut stmt = tx.prepare(r#"UPDATE asset_data SET "TD-no" = ?1 WHERE id = ?2"#)?;
+            for row in &rows {
+                stmt.execute(params![row.trading_day_no, row.id])?;
+            }
+        }
+        tx.commit()?;
+        Ok::<_, anyhow::Error>(refresh_dependents(&mut conn, &state, &asset_name))
+    });
+
+    match result {
+        Ok(warnings) => {
+            let assigned = match calendar {
+                Some(code) => format!("Assigned trading calendar '{}' to '{}'", code, asset_name),
+                None => format!("Cleared trading calendar for '{}'", asset_name),
+            };
+            ok_msg(with_dependents_note(assigned, &warnings))
+        }
+        Err(e) => err(e),
+    }
+}
+
+#[tauri::command(rename = "get_holiday_effects")]
+pub fn get_holiday_effects(state: State<'_, DbState>, asset_name: String, calendar: Option<String>) -> ApiResult<Vec<calendar::HolidayEffect>> {
+    let result = with_db_lock(&state, || {
+        db::ensure_schema(&state.db_path)?;
+        let conn = conn(&state)?;
+        let cal = match calendar {
+            Some(code) => calendar::find_calendar(&calendar::calendars_path(&state.db_path), &code)?,
+            None => asset_calendar(&conn, &state, &asset_name)?
+                .ok_or_else(|| anyhow::anyhow!("No trading calendar assigned to '{}'", asset_name))?,
+        };
+        let rows = query_asset_bars(&conn, &asset_name)?;
+        Ok::<_, anyhow::Error>(calendar::holiday_effects(&rows, &cal))
+    });
+
+    match result {
+        Ok(v) => ok(v),
+        Err(e) => err(e),
+    }
+}
+
+#[tauri::command(rename = "run_event_study")]
+pub fn run_event_study(
+    state: State<'_, DbState>,
+    asset_name: String,
+    events: EventSource,
+    days_before: usize,
+    days_after: usize,
+    confidence: Option<f64>,
+) -> ApiResult<events::EventStudyResult> {
+    let result = with_db_lock(&state, || {
+        db::ensure_schema(&state.db_path)?;
+        let conn = conn(&state)?;
+        let rows = query_asset_bars(&conn, &asset_name)?;
+
+        let dates: Vec<chrono::NaiveDate> = match &events {
+            EventSource::Dates { dates } => dates
+                .iter()
+                .map(|d| processor::parse_date(&Value::String(d.clone())).ok_or_else(|| anyhow::anyhow!("Invalid event date '{}'", d)))
+                .collect::<anyhow::Result<_>>()?,
+            EventSource::Calendar { calendar: code, holidays } => {
+                let cal = calendar::find_calendar(&calendar::calendars_path(&state.db_path), code)?;
+                match events::year_span(&rows) {
+                    Some((first, last)) => events::calendar_events(&cal, holidays.as_deref(), first, last),
+                    None => vec![],
+                }
+            }
+        };
+
+        Ok::<_, anyhow::Error>(events::event_study(&rows, &dates, days_before, days_after, confidence.unwrap_or(0.95)))
+    });
+
+    match result {
+        Ok(v) => ok(v),
+        Err(e) => err(e),
+    }
+}
+
+#[tauri::command(rename = "get_corporate_actions")]
+pub fn get_corporate_actions(state: State<'_, DbState>, asset_name: String) -> ApiResult<Vec<CorporateAction>> {
+    let result = with_db_lock(&state, || {
+        db::ensure_schema(&state.db_path)?;
+        let conn = conn(&state)?;
+        db::load_corporate_actions(&conn, &asset_name)
+    });
+
+    match result {
+        Ok(v) => ok(v),
+        Err(e) => err(e),
+    }
+}
+
+/// Replaces an asset's splits and dividends and recomputes its back-adjusted prices.
+/// The unadjusted series stays on every row as Raw_Open/Raw_High/Raw_Low/Raw_Close (and Raw_Volume).
+#[tauri::command(rename = "save_corporate_actions")]
+pub fn save_corporate_actions(state: State<'_, DbState>, asset_name: String, actions: Vec<CorporateAction>) -> ApiResult<Vec<ProcessedBar>> {
+    let result = with_db_lock(&state, || {
+        adjustments::validate(&actions)?;
+        db::ensure_schema(&state.db_path)?;
+        let mut conn = conn(&state)?;
+        db::save_corporate_actions(&conn, &asset_name, &actions)?;
+
+        let base: Vec<OhlcBar> = query_source_bars(&conn, &asset_name)?.into_iter().map(|(_, bar)| bar).collect();
+        reprocess_asset(&mut conn, &state, &asset_name, &base)
+    });
+
+    match result {
+        Ok((v, warnings)) => ok_with_warnings(v, warnings),
+        Err(e) => err(e),
+    }
+}
+
+/// Builds a continuous futures asset from stored contract assets and saves it as `asset_name`,
+/// replacing any previous rows for it. Roll dates are kept with the asset.
+#[tauri::command(rename = "build_continuous_futures")]
+pub fn build_continuous_futures(
+    state: State<'_, DbState>,
+    asset_name: String,
+    contracts: Vec<ContractSpec>,
+    rule: RollRule,
+    adjustment: Option<RollAdjustment>,
+) -> ApiResult<rolling::ContinuousSeries<ProcessedBar>> {
+    let result = with_db_lock(&state, || {
+        let target = asset_name.trim();
+        if target.is_empty() {
+            return Err(anyhow::anyhow!("Continuous asset needs a name"));
+        }
+        if contracts.iter().any(|c| c.asset == target) {
+            return Err(anyhow::anyhow!("Continuous series cannot replace one of its contracts"));
+        }
+        db::ensure_schema(&state.db_path)?;
+        let mut conn = conn(&state)?;
+
+        let mut inputs = Vec::with_capacity(contracts.len());
+        for spec in contracts {
+            let rows = query_asset_bars(&conn, &spec.asset)?;
+            inputs.push((spec, rows));
+        }
+        let series = rolling::build_continuous(&inputs, rule, adjustment.unwrap_or_default())?;
+
+        db::set_asset_setting(&conn, target, "rolls", Some(&serde_json::to_string(&series.rolls)?))?;
+        let (rows, warnings) = reprocess_asset(&mut conn, &state, target, &series.rows)?;
+        Ok::<_, anyhow::Error>((rolling::ContinuousSeries { rows, rolls: series.rolls }, warnings))
+    });
+
+    match result {
+        Ok((v, warnings)) => ok_with_warnings(v, warnings),
+        Err(e) => err(e),
+    }
+}
+
+#[tauri::command(rename = "get_roll_dates")]
+pub fn get_roll_dates(state: State<'_, DbState>, asset_name: String) -> ApiResult<Vec<RollEvent>> {
+    let result = with_db_lock(&state, || {
+        db::ensure_schema(&state.db_path)?;
+        let conn = conn(&state)?;
+        match db::get_asset_setting(&conn, &asset_name, "rolls")? {
+            Some(s) => Ok(serde_json::from_str(&s)?),
+            None => Ok(vec![]),
+        }
+    });
+
+    match result {
+        Ok(v) => ok(v),
+        Err(e) => err(e),
+    }
+}
+
+/// Defines (or redefines) a synthetic asset from a formula over stored assets and materializes it.
+/// It is rebuilt whenever one of its components is reprocessed.
+#[tauri::command(rename = "define_synthetic_asset")]
+pub fn define_synthetic_asset(state: State<'_, DbState>, asset_name: String, formula: SyntheticFormula) -> ApiResult<Vec<ProcessedBar>> {
+    let result = with_db_lock(&state, || {
+        formula.validate()?;
+        let target = asset_name.trim();
+        if target.is_empty() {
+            return Err(anyhow::anyhow!("Synthetic asset needs a name"));
+        }
+        db::ensure_schema(&state.db_path)?;
+        let mut conn = conn(&state)?;
+        for component in formula.components() {
+            if component == target || depends_on(&conn, component, target)? {
+                return Err(anyhow::anyhow!("'{}' cannot be built from itself (via '{}')", target, component));
+            }
+        }
+
+        db::set_asset_setting(&conn, target, "synthetic", Some(&serde_json::to_string(&formula)?))?;
+        rebuild_synthetic(&mut conn, &state, target, &formula)
+    });
+
+    match result {
+        Ok((v, warnings)) => ok_with_warnings(v, warnings),
+        Err(e) => err(e),
+    }
+}
 
-        // Apply updates
-        for (k, v) in updated_row {
-            base[i].1.insert(k, v);
+#[tauri::command(rename = "get_synthetic_definition")]
+pub fn get_synthetic_definition(state: State<'_, DbState>, asset_name: String) -> ApiResult<Option<SyntheticFormula>> {
+    let result = with_db_lock(&state, || {
+        db::ensure_schema(&state.db_path)?;
+        let conn = conn(&state)?;
+        synthetic_formula(&conn, &asset_name)
+    });
+
+    match result {
+        Ok(v) => ok(v),
+        Err(e) => err(e),
+    }
+}
+
+fn synthetic_formula(conn: &Connection, asset_name: &str) -> anyhow::Result<Option<SyntheticFormula>> {
+    match db::get_asset_setting(conn, asset_name, "synthetic")? {
+        Some(s) => Ok(Some(serde_json::from_str(&s)?)),
+        None => Ok(None),
+    }
+}
+
+/// Whether `asset` is a synthetic asset built (directly or transitively) from `target`.
+fn depends_on(conn: &Connection, asset: &str, target: &str) -> anyhow::Result<bool> {
+    let Some(formula) = synthetic_formula(conn, asset)? else { return Ok(false) };
+    for component in formula.components() {
+        if component == target || depends_on(conn, component, target)? {
+            return Ok(true);
         }
+    }
+    Ok(false)
+}
 
-        let to_process: Vec<Map<String, Value>> = base.into_iter().map(|(_, m)| m).collect();
-        let processed = processor::calculate_derived_columns(&to_process, true);
+fn rebuild_synthetic(
+    conn: &mut Connection,
+    state: &DbState,
+    asset_name: &str,
+    formula: &SyntheticFormula,
+) -> anyhow::Result<(Vec<ProcessedBar>, Vec<String>)> {
+    let mut inputs = HashMap::new();
+    for component in formula.components() {
+        inputs.insert(component.to_string(), query_asset_bars(conn, component)?);
+    }
+    let rows = synthetic::build(formula, &inputs)?;
+    reprocess_asset(conn, state, asset_name, &rows)
+}
 
-        conn.execute("DELETE FROM asset_data WHERE asset = ?1", params![&asset_name])?;
-        insert_processed(&mut conn, &processed, &asset_name)?;
+/// Rebuilds synthetic assets that use `asset_name` as a component, once its changes are committed. A
+/// dependent that fails to rebuild keeps its previous rows and does not undo the change; the failures are
+/// logged and returned for the caller to report.
+fn refresh_dependents(conn: &mut Connection, state: &DbState, asset_name: &str) -> Vec<String> {
+    let definitions = match db::assets_with_setting(conn, "synthetic") {
+        Ok(d) => d,
+        Err(e) => return vec![format!("synthetic definitions could not be read: {}", e)],
+    };
+    let mut failures = Vec::new();
+    for (asset, definition) in definitions {
+        let rebuilt = serde_json::from_str::<SyntheticFormula>(&definition)
+            .map_err(anyhow::Error::from)
+            .and_then(|formula| {
+                if formula.components().contains(&asset_name) {
+                    rebuild_synthetic(conn, state, &asset, &formula).map(|(_, nested)| nested)
+                } else {
+                    Ok(vec![])
+                }
+            });
+        match rebuilt {
+            Ok(nested) => failures.extend(nested),
+            Err(e) => {
+                eprintln!("Could not rebuild synthetic asset '{}' after '{}' changed: {}", asset, asset_name, e);
+                failures.push(format!("'{}': {}", asset, e));
+            }
+        }
+    }
+    failures
+}
 
-        Ok::<_, anyhow::Error>(processed)
+/// Return (full and rolling) and seasonal-profile correlations between the selected assets.
+#[tauri::command(rename = "get_correlation_matrix")]
+pub fn get_correlation_matrix(state: State<'_, DbState>, asset_names: Vec<String>, window: Option<usize>) -> ApiResult<CorrelationMatrix> {
+    let result = with_db_lock(&state, || {
+        if asset_names.len() < 2 {
+            return Err(anyhow::anyhow!("Select at least two assets to compare"));
+        }
+        db::ensure_schema(&state.db_path)?;
+        let conn = conn(&state)?;
+        let mut inputs = Vec::with_capacity(asset_names.len());
+        for asset in &asset_names {
+            inputs.push((asset.clone(), query_asset_bars(&conn, asset)?));
+        }
+        Ok::<_, anyhow::Error>(correlation::correlation_matrix(&inputs, window.unwrap_or(60)))
     });
 
     match result {
@@ -463,46 +1067,122 @@ pub fn update_row(state: State<'_, DbState>, asset_name: String, row_id: Value,
     }
 }
 
+/// Writes a correlation matrix to xlsx: one sheet per matrix plus the rolling series side by side.
+#[tauri::command(rename = "export_correlation_excel")]
+pub fn export_correlation_excel(matrix: CorrelationMatrix, file_path: String) -> ApiResult<Value> {
+    use rust_xlsxwriter::Workbook;
+
+    let write = || -> anyhow::Result<()> {
+        let mut workbook = Workbook::new();
+        for (name, values) in [("Returns", &matrix.return_correlation), ("Seasonal", &matrix.seasonal_correlation)] {
+            let sheet = workbook.add_worksheet();
+            sheet.set_name(name)?;
+            for (i, asset) in matrix.assets.iter().enumerate() {
+                sheet.write_string(0, i as u16 + 1, asset)?;
+                sheet.write_string(i as u32 + 1, 0, asset)?;
+                for (j, v) in values[i].iter().enumerate() {
+                    if let Some(v) = v {
+                        sheet.write_number(i as u32 + 1, j as u16 + 1, *v)?;
+                    }
+                }
+            }
+        }
+
+        let sheet = workbook.add_worksheet();
+        sheet.set_name(format!("Rolling {}", matrix.window))?;
+        sheet.write_string(0, 0, "Date")?;
+        let mut by_date: std::collections::BTreeMap<&str, Vec<Option<f64>>> = std::collections::BTreeMap::new();
+        for (col, pair) in matrix.rolling.iter().enumerate() {
+            sheet.write_string(0, col as u16 + 1, format!("{} / {}", pair.a, pair.b))?;
+            for p in &pair.points {
+                by_date.entry(p.date.as_str()).or_insert_with(|| vec![None; matrix.rolling.len()])[col] = Some(p.value);
+            }
+        }
+        for (row, (date, values)) in by_date.iter().enumerate() {
+            sheet.write_string(row as u32 + 1, 0, *date)?;
+            for (col, v) in values.iter().enumerate() {
+                if let Some(v) = v {
+                    sheet.write_number(row as u32 + 1, col as u16 + 1, *v)?;
+                }
+            }
+        }
+
+        workbook.save(&file_path)?;
+        Ok(())
+    };
+
+    match write() {
+        Ok(_) => ApiResult { success: true, data: None, message: None, error: None },
+        Err(e) => err(e),
+    }
+}
+
+#[tauri::command(rename = "add_row")]
+pub fn add_row(state: State<'_, DbState>, asset_name: String, new_row: OhlcBar) -> ApiResult<Vec<ProcessedBar>> {
+    // Fetch base rows, append, recalc, replace asset data
+    let result = with_db_lock(&state, || {
+        db::ensure_schema(&state.db_path)?;
+        let mut conn = conn(&state)?;
+
+        let mut base: Vec<OhlcBar> = query_source_bars(&conn, &asset_name)?.into_iter().map(|(_, bar)| bar).collect();
+        base.push(new_row);
+
+        reprocess_asset(&mut conn, &state, &asset_name, &base)
+    });
+
+    match result {
+        Ok((v, warnings)) => ok_with_warnings(v, warnings),
+        Err(e) => err(e),
+    }
+}
+
+#[tauri::command(rename = "update_row")]
+pub fn update_row(state: State<'_, DbState>, asset_name: String, row_id: Value, updated_row: Map<String, Value>) -> ApiResult<Vec<ProcessedBar>> {
+    let row_id_str = row_id.to_string();
+
+    let result = with_db_lock(&state, || {
+        db::ensure_schema(&state.db_path)?;
+        let mut conn = conn(&state)?;
+
+        let mut base = query_source_bars(&conn, &asset_name)?;
+
+        let idx = base.iter().position(|(id, _)| id.to_string() == row_id_str.trim_matches('"'));
+        let Some(i) = idx else { return Err(anyhow::anyhow!("Row not found")); };
+
+        // Apply updates (the edit is a partial row, in the adjusted prices the table shows)
+        base[i].1 = adjustments::apply_edit(&base[i].1, updated_row)?;
+
+        let to_process: Vec<OhlcBar> = base.into_iter().map(|(_, bar)| bar).collect();
+        reprocess_asset(&mut conn, &state, &asset_name, &to_process)
+    });
+
+    match result {
+        Ok((v, warnings)) => ok_with_warnings(v, warnings),
+        Err(e) => err(e),
+    }
+}
+
 #[tauri::command(rename = "delete_row")]
-pub fn delete_row(state: State<'_, DbState>, asset_name: String, row_id: Value) -> ApiResult<Vec<Map<String, Value>>> {
+pub fn delete_row(state: State<'_, DbState>, asset_name: String, row_id: Value) -> ApiResult<Vec<ProcessedBar>> {
     let row_id_str = row_id.to_string();
 
     let result = with_db_lock(&state, || {
         db::ensure_schema(&state.db_path)?;
         let mut conn = conn(&state)?;
 
-        let base: Vec<(i64, Map<String, Value>)> = {
-            let mut stmt = conn.prepare("SELECT id, Date, Open, High, Low, Close FROM asset_data WHERE asset = ?1 ORDER BY Date")?;
-            let rows = stmt.query_map(params![&asset_name], |r| {
-                    let id: i64 = r.get(0)?;
-                    let mut m = Map::new();
-                    let date: Option<String> = r.get(1)?;
-                    m.insert("Date".to_string(), date.map(Value::String).unwrap_or(Value::Null));
-                    for (i, key) in [(2, "Open"), (3, "High"), (4, "Low"), (5, "Close")] {
-                        let v: Option<f64> = r.get(i)?;
-                        m.insert(key.to_string(), v.and_then(serde_json::Number::from_f64).map(Value::Number).unwrap_or(Value::Null));
-                    }
-                    Ok((id, m))
-                })?;
-            rows.collect::<Result<Vec<_>, _>>()?
-        };
+        let base = query_source_bars(&conn, &asset_name)?;
 
-        let keep: Vec<Map<String, Value>> = base
+        let keep: Vec<OhlcBar> = base
             .into_iter()
             .filter(|(id, _)| id.to_string() != row_id_str.trim_matches('"'))
-            .map(|(_, m)| m)
+            .map(|(_, bar)| bar)
             .collect();
 
-        let processed = processor::calculate_derived_columns(&keep, true);
-
-        conn.execute("DELETE FROM asset_data WHERE asset = ?1", params![&asset_name])?;
-        insert_processed(&mut conn, &processed, &asset_name)?;
-
-        Ok::<_, anyhow::Error>(processed)
+        reprocess_asset(&mut conn, &state, &asset_name, &keep)
     });
 
     match result {
-        Ok(v) => ok(v),
+        Ok((v, warnings)) => ok_with_warnings(v, warnings),
         Err(e) => err(e),
     }
 }
@@ -520,55 +1200,59 @@ pub fn clear_database(state: State<'_, DbState>) -> ApiResult<Value> {
     }
 }
 
-fn insert_processed(conn: &mut Connection, data: &[Map<String, Value>], asset_name: &str) -> anyhow::Result<()> {
-    let tx = conn.transaction()?;
-    let sql = r#"
-      INSERT INTO asset_data
-        (Date, Open, High, Low, Close, "%change", "M-no", normalized, Average_Norm, True_Seasonal, asset, processed_date)
-      VALUES
-        (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
-    "#;
-    {
-        let mut stmt = tx.prepare(sql)?;
-        let now = chrono::Utc::now().to_rfc3339();
-
-        for row in data {
-            let date = row.get("Date").and_then(|v| v.as_str()).unwrap_or("");
-            let open = row.get("Open").and_then(|v| v.as_f64()).unwrap_or(0.0);
-            let high = row.get("High").and_then(|v| v.as_f64()).unwrap_or(0.0);
-            let low = row.get("Low").and_then(|v| v.as_f64()).unwrap_or(0.0);
-            let close = row.get("Close").and_then(|v| v.as_f64()).unwrap_or(0.0);
-            let pct = row.get("%change").and_then(|v| v.as_f64()).unwrap_or(0.0);
-            let m_no = row.get("M-no").and_then(|v| v.as_i64()).unwrap_or(0);
-            let norm = row.get("normalized").and_then(|v| v.as_f64()).unwrap_or(0.0);
-            let avg_norm = row.get("Average_Norm").and_then(|v| v.as_f64()).unwrap_or(0.0);
-            let true_seasonal = row.get("True_Seasonal").and_then(|v| v.as_f64()).unwrap_or(0.0);
-
-            stmt.execute(params![
-                date,
-                open,
-                high,
-                low,
-                close,
-                pct,
-                m_no,
-                norm,
-                avg_norm,
-                true_seasonal,
-                asset_name,
-                now
-            ])?;
-        }
+/// Refills gaps and recomputes adjusted prices and derived, indicator and trading-day columns for an edited asset
+/// and replaces its rows. Also returns the dependent assets that could not be rebuilt.
+fn reprocess_asset(
+    conn: &mut Connection,
+    state: &DbState,
+    asset_name: &str,
+    base: &[OhlcBar],
+) -> anyhow::Result<(Vec<ProcessedBar>, Vec<String>)> {
+    let options = db::load_processing_options(conn, asset_name)?;
+    let cal = asset_calendar(conn, state, asset_name)?;
+    // Gaps are filled from raw prices; adjustment then applies to filled bars like any other
+    let raw = adjustments::raw_prices(base);
+    let filled = match &cal {
+        Some(cal) => gaps::fill_gaps(&raw, options.fill_policy, &cal.session_checker()),
+        None => gaps::fill_gaps(&raw, options.fill_policy, &gaps::is_weekday),
+    };
+    let adjusted = adjustments::adjust_prices(&filled, &db::load_corporate_actions(conn, asset_name)?)?;
+    let mut processed = processor::calculate_derived_columns(&adjusted, false, &options);
+    let specs = db::load_indicator_specs(conn, asset_name)?;
+    indicators::apply_indicators(&mut processed, &specs);
+    if let Some(cal) = &cal {
+        calendar::apply_trading_day(&mut processed, cal);
     }
 
+    let tx = conn.transaction()?;
+    tx.execute("DELETE FROM asset_data WHERE asset = ?1", params![asset_name])?;
+    db::insert_bars(&tx, &processed, asset_name, &indicators::column_names(&specs))?;
     tx.commit()?;
-    Ok(())
+    let warnings = refresh_dependents(conn, state, asset_name);
+    Ok((processed, warnings))
 }
 
+/// Exports rows to xlsx. When processing options are given (or can be loaded for `asset_name`),
+/// a "Processing" sheet records how the derived columns were computed.
 #[tauri::command(rename = "export_excel")]
-pub fn export_excel(data: Vec<Map<String, Value>>, file_path: String) -> ApiResult<Value> {
+pub fn export_excel(
+    state: State<'_, DbState>,
+    data: Vec<ProcessedBar>,
+    file_path: String,
+    asset_name: Option<String>,
+    options: Option<ProcessingOptions>,
+) -> ApiResult<Value> {
     use rust_xlsxwriter::Workbook;
 
+    let options = match (options, asset_name) {
+        (Some(o), _) => Some(o),
+        (None, Some(asset)) => match with_db_lock(&state, || db::load_processing_options(&conn(&state)?, &asset)) {
+            Ok(o) => Some(o),
+            Err(e) => return err(e),
+        },
+        (None, None) => None,
+    };
+
     let mut workbook = Workbook::new();
     let worksheet = workbook.add_worksheet();
 
@@ -579,27 +1263,14 @@ pub fn export_excel(data: Vec<Map<String, Value>>, file_path: String) -> ApiResu
         return ApiResult { success: true, data: None, message: None, error: None };
     }
 
-    let preferred_headers = [
-        "Date",
-        "Open",
-        "High",
-        "Low",
-        "Close",
-        "%change",
-        "M-no",
-        "normalized",
-        "Average_Norm",
-        "True_Seasonal",
-    ];
-
-    let mut headers: Vec<String> = preferred_headers
-        .iter()
-        .filter(|k| data[0].contains_key(**k))
+    let data: Vec<Map<String, Value>> = data.iter().map(ProcessedBar::to_map).collect();
+    let mut headers: Vec<String> = model::export_columns()
+        .filter(|k| data.iter().any(|row| row.contains_key(*k)))
         .map(|s| s.to_string())
         .collect();
 
-    // Add any extra keys at the end.
-    for k in data[0].keys() {
+    // Add any extra keys at the end; extra source columns may only be present on some rows.
+    for k in data.iter().flat_map(|row| row.keys()) {
         if !headers.iter().any(|h| h == k) {
             headers.push(k.clone());
         }
@@ -628,12 +1299,23 @@ pub fn export_excel(data: Vec<Map<String, Value>>, file_path: String) -> ApiResu
                     let _ = worksheet.write_string(excel_row, col_u16, s);
                 }
                 other => {
-                    let _ = worksheet.write_string(excel_row, col_u16, &other.to_string());
+                    let _ = worksheet.write_string(excel_row, col_u16, other.to_string());
                 }
             }
         }
     }
 
+    if let Some(options) = options {
+        let sheet = workbook.add_worksheet();
+        let _ = sheet.set_name("Processing");
+        let _ = sheet.write_string(0, 0, "Setting");
+        let _ = sheet.write_string(0, 1, "Value");
+        for (i, (k, v)) in options.summary().into_iter().enumerate() {
+            let _ = sheet.write_string(i as u32 + 1, 0, k);
+            let _ = sheet.write_string(i as u32 + 1, 1, v);
+        }
+    }
+
     match workbook.save(&file_path) {
         Ok(_) => ApiResult { success: true, data: None, message: None, error: None },
         Err(e) => err(e.to_string()),
@@ -0,0 +1,168 @@
+use crate::model::ProcessedBar;
+use crate::processor::{close_series, pearson, seasonal_composite, year_paths};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RollingPoint {
+    pub date: String,
+    pub value: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RollingCorrelation {
+    pub a: String,
+    pub b: String,
+    pub points: Vec<RollingPoint>,
+}
+
+/// Square matrices indexed like `assets`; `None` where two assets share too little history.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CorrelationMatrix {
+    pub assets: Vec<String>,
+    /// Correlation of daily close-to-close returns on shared dates.
+    pub return_correlation: Vec<Vec<Option<f64>>>,
+    /// Number of shared return observations behind each return correlation.
+    pub overlap: Vec<Vec<usize>>,
+    /// Correlation of the average year-to-date seasonal paths, day of year by day of year.
+    pub seasonal_correlation: Vec<Vec<Option<f64>>>,
+    pub window: usize,
+    /// Rolling return correlation for every pair (i < j).
+    pub rolling: Vec<RollingCorrelation>,
+}
+
+fn returns(input: &[ProcessedBar]) -> BTreeMap<NaiveDate, f64> {
+    close_series(input)
+        .windows(2)
+        .filter(|w| w[0].1 != 0.0)
+        .map(|w| (w[1].0, (w[1].1 / w[0].1 - 1.0) * 100.0))
+        .collect()
+}
+
+pub fn correlation_matrix(inputs: &[(String, Vec<ProcessedBar>)], window: usize) -> CorrelationMatrix {
+    let n = inputs.len();
+    let window = window.max(2);
+    let all_returns: Vec<BTreeMap<NaiveDate, f64>> = inputs.iter().map(|(_, rows)| returns(rows)).collect();
+    let profiles: Vec<BTreeMap<u32, f64>> = inputs
+        .iter()
+        .map(|(_, rows)| seasonal_composite(&year_paths(&close_series(rows))).into_iter().collect())
+        .collect();
+
+    let mut return_correlation = vec![vec![None; n]; n];
+    let mut overlap = vec![vec![0; n]; n];
+    let mut seasonal_correlation = vec![vec![None; n]; n];
+    let mut rolling = Vec::new();
+
+    for i in 0..n {
+        for j in i..n {
+            let shared: Vec<(NaiveDate, f64, f64)> = all_returns[i]
+                .iter()
+                .filter_map(|(d, x)| all_returns[j].get(d).map(|y| (*d, *x, *y)))
+                .collect();
+            let xs: Vec<f64> = shared.iter().map(|s| s.1).collect();
+            let ys: Vec<f64> = shared.iter().map(|s| s.2).collect();
+            let r = pearson(&xs, &ys);
+            return_correlation[i][j] = r;
+            return_correlation[j][i] = r;
+            overlap[i][j] = shared.len();
+            overlap[j][i] = shared.len();
+
+            let (px, py): (Vec<f64>, Vec<f64>) = profiles[i]
+                .iter()
+                .filter_map(|(day, x)| profiles[j].get(day).map(|y| (*x, *y)))
+                .unzip();
+            let s = pearson(&px, &py);
+            seasonal_correlation[i][j] = s;
+            seasonal_correlation[j][i] = s;
+
+            if i != j {
+                let points = (window..=shared.len())
+                    .filter_map(|end| {
+                        let value = pearson(&xs[end - window..end], &ys[end - window..end])?;
+                        Some(RollingPoint { date: shared[end - 1].0.format("%Y-%m-%d").to_string(), value })
+                    })
+                    .collect();
+                rolling.push(RollingCorrelation { a: inputs[i].0.clone(), b: inputs[j].0.clone(), points });
+            }
+        }
+    }
+
+    CorrelationMatrix {
+        assets: inputs.iter().map(|(name, _)| name.clone()).collect(),
+        return_correlation,
+        overlap,
+        seasonal_correlation,
+        window,
+        rolling,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::test_bar;
+
+    fn asset(name: &str, closes: &[(u32, f64)]) -> (String, Vec<ProcessedBar>) {
+        (name.to_string(), closes.iter().map(|(d, c)| test_bar(&format!("2024-01-{:02}", d), *c)).collect())
+    }
+
+    const A: [f64; 6] = [100.0, 102.0, 101.0, 105.0, 104.0, 108.0];
+
+    /// An asset whose every daily return is the opposite of A's.
+    fn mirror() -> Vec<(u32, f64)> {
+        let mut price = 50.0;
+        let mut out = vec![(1, price)];
+        for (d, w) in (2..).zip(A.windows(2)) {
+            price *= 1.0 - (w[1] / w[0] - 1.0);
+            out.push((d, price));
+        }
+        out
+    }
+
+    fn close(value: Option<f64>, expected: f64) -> bool {
+        value.is_some_and(|v| (v - expected).abs() < 1e-9)
+    }
+
+    #[test]
+    fn scaled_and_mirrored_assets_correlate_perfectly() {
+        let a: Vec<(u32, f64)> = (1..).zip(A).collect();
+        let doubled: Vec<(u32, f64)> = a.iter().map(|(d, c)| (*d, c * 2.0)).collect();
+        let inputs = [asset("A", &a), asset("2A", &doubled), asset("M", &mirror()), asset("FLAT", &[(1, 5.0), (2, 5.0), (3, 5.0)])];
+        let m = correlation_matrix(&inputs, 3);
+
+        assert!(close(m.return_correlation[0][0], 1.0));
+        assert!(close(m.return_correlation[0][1], 1.0));
+        assert!(close(m.return_correlation[0][2], -1.0) && close(m.return_correlation[2][0], -1.0));
+        assert_eq!(m.overlap[0][2], 5);
+        // A flat series has no variance to correlate.
+        assert_eq!(m.return_correlation[0][3], None);
+        assert_eq!(m.overlap[0][3], 2);
+        // Same year-to-date path, day by day.
+        assert!(close(m.seasonal_correlation[0][1], 1.0));
+    }
+
+    #[test]
+    fn returns_are_matched_on_shared_dates() {
+        // B skips Jan 4, so its Jan 5 return spans two sessions and Jan 4 is not shared.
+        let a: Vec<(u32, f64)> = (1..).zip(A).collect();
+        let b = [(1, 10.0), (2, 11.0), (3, 10.0), (5, 12.0), (6, 13.0)];
+        let m = correlation_matrix(&[asset("A", &a), asset("B", &b)], 2);
+        assert_eq!(m.overlap[0][1], 4);
+        assert_eq!(m.overlap[0][0], 5);
+    }
+
+    #[test]
+    fn rolling_windows_end_on_each_shared_date() {
+        let a: Vec<(u32, f64)> = (1..).zip(A).collect();
+        let m = correlation_matrix(&[asset("A", &a), asset("M", &mirror())], 3);
+        assert_eq!(m.rolling.len(), 1);
+        let rolling = &m.rolling[0];
+        assert_eq!((rolling.a.as_str(), rolling.b.as_str()), ("A", "M"));
+        let dates: Vec<&str> = rolling.points.iter().map(|p| p.date.as_str()).collect();
+        assert_eq!(dates, vec!["2024-01-04", "2024-01-05", "2024-01-06"]);
+        assert!(rolling.points.iter().all(|p| (p.value + 1.0).abs() < 1e-9));
+        // Windows shorter than two returns are widened to two.
+        assert_eq!(correlation_matrix(&[asset("A", &a), asset("M", &mirror())], 0).window, 2);
+    }
+}
@@ -0,0 +1,101 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// Selects which years feed a seasonal composite. Filters nest through `all_of`, `any_of` and `not`.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum YearFilter {
+    /// US presidential election years (divisible by 4).
+    Election,
+    /// Position in the four-year presidential cycle: 1 post-election, 2 midterm, 3 pre-election, 4 election.
+    PresidentialCycle { position: u8 },
+    /// Years whose last digit is `digit`, e.g. 5 for 1995, 2005, 2015.
+    Decennial { digit: u8 },
+    /// Years following a calendar year whose last close was below the one before it.
+    AfterDownYear,
+    /// Years following a calendar year whose last close was above the one before it.
+    AfterUpYear,
+    Years { years: Vec<i32> },
+    AllOf { filters: Vec<YearFilter> },
+    AnyOf { filters: Vec<YearFilter> },
+    Not { filter: Box<YearFilter> },
+}
+
+impl YearFilter {
+    /// `annual_returns` maps a year to its close-to-close change from the previous year's last close.
+    pub fn matches(&self, year: i32, annual_returns: &BTreeMap<i32, f64>) -> bool {
+        match self {
+            YearFilter::Election => year.rem_euclid(4) == 0,
+            YearFilter::PresidentialCycle { position } => {
+                let pos = match year.rem_euclid(4) {
+                    0 => 4,
+                    r => r,
+                };
+                pos == *position as i32
+            }
+            YearFilter::Decennial { digit } => year.rem_euclid(10) == *digit as i32,
+            YearFilter::AfterDownYear => annual_returns.get(&(year - 1)).is_some_and(|r| *r < 0.0),
+            YearFilter::AfterUpYear => annual_returns.get(&(year - 1)).is_some_and(|r| *r > 0.0),
+            YearFilter::Years { years } => years.contains(&year),
+            YearFilter::AllOf { filters } => filters.iter().all(|f| f.matches(year, annual_returns)),
+            YearFilter::AnyOf { filters } => filters.iter().any(|f| f.matches(year, annual_returns)),
+            YearFilter::Not { filter } => !filter.matches(year, annual_returns),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn filter(value: serde_json::Value) -> YearFilter {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn matching(filter: &YearFilter, annual_returns: &BTreeMap<i32, f64>) -> Vec<i32> {
+        (2015..=2024).filter(|y| filter.matches(*y, annual_returns)).collect()
+    }
+
+    #[test]
+    fn parses_nested_filters() {
+        let parsed = filter(json!({
+            "type": "all_of",
+            "filters": [
+                { "type": "presidential_cycle", "position": 2 },
+                { "type": "not", "filter": { "type": "years", "years": [2022] } },
+            ],
+        }));
+        assert_eq!(
+            parsed,
+            YearFilter::AllOf {
+                filters: vec![
+                    YearFilter::PresidentialCycle { position: 2 },
+                    YearFilter::Not { filter: Box::new(YearFilter::Years { years: vec![2022] }) },
+                ],
+            }
+        );
+        assert_eq!(matching(&parsed, &BTreeMap::new()), vec![2018]);
+        assert!(serde_json::from_value::<YearFilter>(json!({ "type": "decennial" })).is_err());
+        assert!(serde_json::from_value::<YearFilter>(json!({ "type": "leap" })).is_err());
+    }
+
+    #[test]
+    fn calendar_cycles() {
+        let none = BTreeMap::new();
+        assert_eq!(matching(&filter(json!({ "type": "election" })), &none), vec![2016, 2020, 2024]);
+        assert_eq!(matching(&filter(json!({ "type": "presidential_cycle", "position": 4 })), &none), vec![2016, 2020, 2024]);
+        assert_eq!(matching(&filter(json!({ "type": "presidential_cycle", "position": 1 })), &none), vec![2017, 2021]);
+        assert_eq!(matching(&filter(json!({ "type": "decennial", "digit": 5 })), &none), vec![2015]);
+        assert!(filter(json!({ "type": "decennial", "digit": 5 })).matches(1995, &none));
+    }
+
+    #[test]
+    fn previous_year_direction() {
+        let returns = BTreeMap::from([(2017, 12.0), (2018, -6.0), (2019, 0.0), (2022, -19.0)]);
+        assert_eq!(matching(&filter(json!({ "type": "after_down_year" })), &returns), vec![2019, 2023]);
+        assert_eq!(matching(&filter(json!({ "type": "after_up_year" })), &returns), vec![2018]);
+        let either = filter(json!({ "type": "any_of", "filters": [{ "type": "after_down_year" }, { "type": "election" }] }));
+        assert_eq!(matching(&either, &returns), vec![2016, 2019, 2020, 2023, 2024]);
+    }
+}
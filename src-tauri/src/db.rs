@@ -1,4 +1,10 @@
-use rusqlite::Connection;
+use crate::adjustments::{ActionKind, CorporateAction};
+use crate::indicators::IndicatorSpec;
+use crate::model::{ColumnType, OhlcBar, ProcessedBar, COLUMNS};
+use crate::processor::ProcessingOptions;
+use rusqlite::types::{Type, Value as SqlValue};
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+use serde_json::{Map, Value};
 use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use tauri::Manager;
@@ -16,25 +22,42 @@ pub fn default_db_path(app: &tauri::App) -> anyhow::Result<PathBuf> {
 
 pub fn ensure_schema(db_path: &Path) -> anyhow::Result<()> {
     let conn = Connection::open(db_path)?;
+    let columns: Vec<String> = COLUMNS.iter().map(|(name, kind)| format!("\"{}\" {}", name, kind.sql())).collect();
+    conn.execute_batch(&format!(
+        "CREATE TABLE IF NOT EXISTS asset_data (id INTEGER PRIMARY KEY AUTOINCREMENT, {});",
+        columns.join(", ")
+    ))?;
     conn.execute_batch(
         r#"
-        CREATE TABLE IF NOT EXISTS asset_data (
+        CREATE TABLE IF NOT EXISTS asset_indicators (
+          asset TEXT PRIMARY KEY,
+          specs TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS asset_settings (
+          asset TEXT NOT NULL,
+          key TEXT NOT NULL,
+          value TEXT NOT NULL,
+          PRIMARY KEY (asset, key)
+        );
+
+        CREATE TABLE IF NOT EXISTS corporate_actions (
           id INTEGER PRIMARY KEY AUTOINCREMENT,
-          Date TEXT,
-          Open REAL,
-          High REAL,
-          Low REAL,
-          Close REAL,
-          "%change" REAL,
-          "M-no" INTEGER,
-          normalized REAL,
-          Average_Norm REAL,
-          True_Seasonal REAL,
-          asset TEXT,
-          processed_date TEXT
+          asset TEXT NOT NULL,
+          date TEXT NOT NULL,
+          kind TEXT NOT NULL,
+          value REAL NOT NULL
         );
         "#,
     )?;
+    // Databases created before these columns existed.
+    let existing: Vec<String> = conn
+        .prepare("PRAGMA table_info(asset_data)")?
+        .query_map([], |r| r.get::<_, String>(1))?
+        .collect::<Result<_, _>>()?;
+    for (name, kind) in COLUMNS.iter().filter(|(name, _)| !existing.iter().any(|c| c == name)) {
+        conn.execute(&format!("ALTER TABLE asset_data ADD COLUMN \"{}\" {}", name, kind.sql()), [])?;
+    }
     Ok(())
 }
 
@@ -46,8 +69,165 @@ pub fn with_conn<T>(db_path: &Path, f: impl FnOnce(&Connection) -> anyhow::Resul
 pub fn clear_all(db_path: &Path) -> anyhow::Result<()> {
     with_conn(db_path, |conn| {
         conn.execute("DROP TABLE IF EXISTS asset_data", [])?;
+        conn.execute("DROP TABLE IF EXISTS asset_indicators", [])?;
+        conn.execute("DROP TABLE IF EXISTS asset_settings", [])?;
+        conn.execute("DROP TABLE IF EXISTS corporate_actions", [])?;
         Ok(())
     })?;
     ensure_schema(db_path)?;
     Ok(())
 }
+
+pub fn load_indicator_specs(conn: &Connection, asset: &str) -> anyhow::Result<Vec<IndicatorSpec>> {
+    let specs: Option<String> = conn
+        .query_row("SELECT specs FROM asset_indicators WHERE asset = ?1", params![asset], |r| r.get(0))
+        .optional()?;
+    match specs {
+        Some(s) => Ok(serde_json::from_str(&s)?),
+        None => Ok(vec![]),
+    }
+}
+
+pub fn save_indicator_specs(conn: &Connection, asset: &str, specs: &[IndicatorSpec]) -> anyhow::Result<()> {
+    if specs.is_empty() {
+        conn.execute("DELETE FROM asset_indicators WHERE asset = ?1", params![asset])?;
+    } else {
+        conn.execute(
+            "INSERT OR REPLACE INTO asset_indicators (asset, specs) VALUES (?1, ?2)",
+            params![asset, serde_json::to_string(specs)?],
+        )?;
+    }
+    Ok(())
+}
+
+pub fn get_asset_setting(conn: &Connection, asset: &str, key: &str) -> anyhow::Result<Option<String>> {
+    Ok(conn
+        .query_row(
+            "SELECT value FROM asset_settings WHERE asset = ?1 AND key = ?2",
+            params![asset, key],
+            |r| r.get(0),
+        )
+        .optional()?)
+}
+
+pub fn set_asset_setting(conn: &Connection, asset: &str, key: &str, value: Option<&str>) -> anyhow::Result<()> {
+    match value {
+        Some(v) => conn.execute(
+            "INSERT OR REPLACE INTO asset_settings (asset, key, value) VALUES (?1, ?2, ?3)",
+            params![asset, key, v],
+        )?,
+        None => conn.execute("DELETE FROM asset_settings WHERE asset = ?1 AND key = ?2", params![asset, key])?,
+    };
+    Ok(())
+}
+
+/// Every (asset, value) pair stored under a setting key.
+pub fn assets_with_setting(conn: &Connection, key: &str) -> anyhow::Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare("SELECT asset, value FROM asset_settings WHERE key = ?1 ORDER BY asset")?;
+    let rows = stmt
+        .query_map(params![key], |r| Ok((r.get(0)?, r.get(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rows)
+}
+
+pub fn load_processing_options(conn: &Connection, asset: &str) -> anyhow::Result<ProcessingOptions> {
+    match get_asset_setting(conn, asset, "processing")? {
+        Some(s) => Ok(serde_json::from_str(&s)?),
+        None => Ok(ProcessingOptions::default()),
+    }
+}
+
+pub fn save_processing_options(conn: &Connection, asset: &str, options: &ProcessingOptions) -> anyhow::Result<()> {
+    set_asset_setting(conn, asset, "processing", Some(&serde_json::to_string(options)?))
+}
+
+pub fn load_corporate_actions(conn: &Connection, asset: &str) -> anyhow::Result<Vec<CorporateAction>> {
+    let mut stmt = conn.prepare("SELECT date, kind, value FROM corporate_actions WHERE asset = ?1 ORDER BY date, id")?;
+    let rows = stmt
+        .query_map(params![asset], |r| Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?, r.get::<_, f64>(2)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    rows.into_iter()
+        .map(|(date, kind, value)| Ok(CorporateAction { date, kind: ActionKind::from_parts(&kind, value)? }))
+        .collect()
+}
+
+/// Replaces all corporate actions stored for an asset.
+pub fn save_corporate_actions(conn: &Connection, asset: &str, actions: &[CorporateAction]) -> anyhow::Result<()> {
+    conn.execute("DELETE FROM corporate_actions WHERE asset = ?1", params![asset])?;
+    let mut stmt = conn.prepare("INSERT INTO corporate_actions (asset, date, kind, value) VALUES (?1, ?2, ?3, ?4)")?;
+    for a in actions {
+        stmt.execute(params![asset, a.date, a.kind.name(), a.kind.value()])?;
+    }
+    Ok(())
+}
+
+/// Reads an `asset_data` row (selected with `*`) into a bar. With `include_indicators` false the stored
+/// indicator values are left out, leaving the source bar plus derived columns.
+fn read_row(row: &Row, include_indicators: bool) -> rusqlite::Result<ProcessedBar> {
+    let mut m = Map::new();
+    m.insert("id".to_string(), Value::from(row.get::<_, i64>("id")?));
+    for (name, kind) in COLUMNS {
+        let value = match kind {
+            ColumnType::Real => row.get::<_, Option<f64>>(name)?.and_then(serde_json::Number::from_f64).map(Value::Number),
+            ColumnType::Integer => row.get::<_, Option<i64>>(name)?.map(Value::from),
+            ColumnType::Text => row.get::<_, Option<String>>(name)?.map(Value::String),
+            ColumnType::Json => {
+                let json: Option<String> = row.get(name)?;
+                if let Some(Ok(Value::Object(values))) = json.filter(|_| include_indicators || name != "indicators").map(|s| serde_json::from_str::<Value>(&s)) {
+                    m.extend(values);
+                }
+                continue;
+            }
+        };
+        if let Some(v) = value {
+            m.insert(name.to_string(), v);
+        }
+    }
+    ProcessedBar::from_map(&m).map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, Type::Text, Box::new(e)))
+}
+
+pub fn read_bar(row: &Row) -> rusqlite::Result<ProcessedBar> {
+    read_row(row, true)
+}
+
+/// The stored source bar (prices and extra columns) with its row id, for reprocessing after an edit.
+pub fn read_source_bar(row: &Row) -> rusqlite::Result<(i64, OhlcBar)> {
+    let bar = read_row(row, false)?;
+    Ok((bar.id.unwrap_or_default(), bar.bar))
+}
+
+fn json_column(values: Map<String, Value>) -> SqlValue {
+    if values.is_empty() { SqlValue::Null } else { SqlValue::Text(Value::Object(values).to_string()) }
+}
+
+/// Appends bars for `asset`. Keys named in `indicator_columns` go to the `indicators` column, any other
+/// key outside the model to `extra`.
+pub fn insert_bars(conn: &Connection, bars: &[ProcessedBar], asset: &str, indicator_columns: &[String]) -> anyhow::Result<()> {
+    let names: Vec<String> = COLUMNS.iter().map(|(name, _)| format!("\"{}\"", name)).collect();
+    let placeholders: Vec<String> = (1..=COLUMNS.len()).map(|i| format!("?{}", i)).collect();
+    let mut stmt = conn.prepare(&format!("INSERT INTO asset_data ({}) VALUES ({})", names.join(", "), placeholders.join(", ")))?;
+    let now = chrono::Utc::now().to_rfc3339();
+
+    for bar in bars {
+        let (indicators, extra): (Map<String, Value>, Map<String, Value>) =
+            bar.bar.extra.clone().into_iter().partition(|(k, _)| indicator_columns.contains(k));
+        let stored = ProcessedBar {
+            bar: OhlcBar { extra: Map::new(), ..bar.bar.clone() },
+            asset: Some(asset.to_string()),
+            processed_date: Some(now.clone()),
+            ..bar.clone()
+        };
+        let row = stored.to_map();
+        let values = COLUMNS.iter().map(|(name, kind)| match (kind, row.get(*name)) {
+            (ColumnType::Json, _) if *name == "indicators" => json_column(indicators.clone()),
+            (ColumnType::Json, _) => json_column(extra.clone()),
+            (_, None | Some(Value::Null)) => SqlValue::Null,
+            (ColumnType::Real, Some(v)) => v.as_f64().map_or(SqlValue::Null, SqlValue::Real),
+            (ColumnType::Integer, Some(v)) => v.as_i64().map_or(SqlValue::Null, SqlValue::Integer),
+            (ColumnType::Text, Some(Value::String(s))) => SqlValue::Text(s.clone()),
+            (ColumnType::Text, Some(v)) => SqlValue::Text(v.to_string()),
+        });
+        stmt.execute(params_from_iter(values))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,199 @@
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How the trend is removed from closes before seasonal averaging.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DetrendMethod {
+    /// Least-squares line fitted separately to each calendar year.
+    LinearPerYear,
+    /// Centred moving average over `window` bars.
+    MovingAverage {
+        #[serde(default = "default_window")]
+        window: usize,
+    },
+    /// STL-style loop: moving-average trend and monthly cycle-subseries seasonal, refined `iterations` times.
+    Stl {
+        #[serde(default = "default_window")]
+        window: usize,
+        #[serde(default = "default_iterations")]
+        iterations: usize,
+    },
+}
+
+fn default_window() -> usize {
+    252
+}
+
+fn default_iterations() -> usize {
+    2
+}
+
+impl DetrendMethod {
+    pub fn describe(&self) -> String {
+        match self {
+            DetrendMethod::LinearPerYear => "Linear per year".to_string(),
+            DetrendMethod::MovingAverage { window } => format!("Moving average ({} bars)", window),
+            DetrendMethod::Stl { window, iterations } => format!("STL-style ({} bars, {} passes)", window, iterations),
+        }
+    }
+}
+
+/// close = trend + seasonal + residual, bar by bar; NaN where the close is missing.
+pub struct Decomposition {
+    pub trend: Vec<f64>,
+    pub seasonal: Vec<f64>,
+    pub residual: Vec<f64>,
+    /// Change of the trend since the previous bar, taken on a single fit: across a year boundary the
+    /// per-year line is extended back rather than differenced against the previous year's line. NaN
+    /// for the first bar.
+    pub trend_change: Vec<f64>,
+}
+
+/// Centred moving average that shrinks at the edges and skips missing values.
+fn moving_average(values: &[f64], window: usize) -> Vec<f64> {
+    let half = window.max(1) / 2;
+    (0..values.len())
+        .map(|i| {
+            let lo = i.saturating_sub(half);
+            let hi = (i + half + 1).min(values.len());
+            let finite: Vec<f64> = values[lo..hi].iter().copied().filter(|v| v.is_finite()).collect();
+            if finite.is_empty() { f64::NAN } else { finite.iter().sum::<f64>() / finite.len() as f64 }
+        })
+        .collect()
+}
+
+/// Per-year least-squares lines: the trend at each bar and the slope (per day) of its year's line.
+fn linear_per_year(dates: &[NaiveDate], closes: &[f64]) -> (Vec<f64>, Vec<f64>) {
+    let mut groups: HashMap<i32, Vec<usize>> = HashMap::new();
+    for (i, d) in dates.iter().enumerate() {
+        groups.entry(d.year()).or_default().push(i);
+    }
+    let mut trend = vec![f64::NAN; closes.len()];
+    let mut slopes = vec![f64::NAN; closes.len()];
+    for idxs in groups.values() {
+        let points: Vec<(f64, f64)> = idxs
+            .iter()
+            .filter(|i| closes[**i].is_finite())
+            .map(|i| (dates[*i].ordinal() as f64, closes[*i]))
+            .collect();
+        if points.is_empty() {
+            continue;
+        }
+        let n = points.len() as f64;
+        let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+        let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+        let var_x: f64 = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+        let slope = if var_x == 0.0 { 0.0 } else { points.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum::<f64>() / var_x };
+        for i in idxs {
+            trend[*i] = mean_y + slope * (dates[*i].ordinal() as f64 - mean_x);
+            slopes[*i] = slope;
+        }
+    }
+    (trend, slopes)
+}
+
+/// Average of `values` per calendar month, centred so the twelve monthly effects average to zero.
+fn monthly_cycle(dates: &[NaiveDate], values: &[f64]) -> Vec<f64> {
+    let mut sum = [0.0; 12];
+    let mut count = [0usize; 12];
+    for (d, v) in dates.iter().zip(values) {
+        if v.is_finite() {
+            sum[d.month0() as usize] += v;
+            count[d.month0() as usize] += 1;
+        }
+    }
+    let means: Vec<Option<f64>> = (0..12).map(|m| if count[m] > 0 { Some(sum[m] / count[m] as f64) } else { None }).collect();
+    let present: Vec<f64> = means.iter().flatten().copied().collect();
+    let centre = if present.is_empty() { 0.0 } else { present.iter().sum::<f64>() / present.len() as f64 };
+    dates.iter().map(|d| means[d.month0() as usize].map_or(f64::NAN, |m| m - centre)).collect()
+}
+
+pub fn decompose(dates: &[NaiveDate], closes: &[f64], method: DetrendMethod) -> Decomposition {
+    let mut slopes = None;
+    let (trend, seasonal) = match method {
+        DetrendMethod::LinearPerYear => {
+            let (trend, per_year) = linear_per_year(dates, closes);
+            slopes = Some(per_year);
+            let detrended: Vec<f64> = closes.iter().zip(&trend).map(|(c, t)| c - t).collect();
+            (trend, monthly_cycle(dates, &detrended))
+        }
+        DetrendMethod::MovingAverage { window } => {
+            let trend = moving_average(closes, window);
+            let detrended: Vec<f64> = closes.iter().zip(&trend).map(|(c, t)| c - t).collect();
+            (trend, monthly_cycle(dates, &detrended))
+        }
+        DetrendMethod::Stl { window, iterations } => {
+            let mut trend = moving_average(closes, window);
+            let mut seasonal = vec![0.0; closes.len()];
+            for _ in 0..iterations.max(1) {
+                let detrended: Vec<f64> = closes.iter().zip(&trend).map(|(c, t)| c - t).collect();
+                seasonal = monthly_cycle(dates, &detrended);
+                let deseasonalized: Vec<f64> = closes.iter().zip(&seasonal).map(|(c, s)| if s.is_finite() { c - s } else { *c }).collect();
+                trend = moving_average(&deseasonalized, window);
+            }
+            (trend, seasonal)
+        }
+    };
+    let residual = closes.iter().zip(&trend).zip(&seasonal).map(|((c, t), s)| c - t - s).collect();
+    let trend_change = (0..trend.len())
+        .map(|i| match (i.checked_sub(1), &slopes) {
+            (None, _) => f64::NAN,
+            (Some(p), Some(slopes)) => slopes[i] * (dates[i] - dates[p]).num_days() as f64,
+            (Some(p), None) => trend[i] - trend[p],
+        })
+        .collect();
+    Decomposition { trend, seasonal, residual, trend_change }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(y: i32, ordinal: u32) -> NaiveDate {
+        NaiveDate::from_yo_opt(y, ordinal).unwrap()
+    }
+
+    fn close_to(a: &[f64], b: &[f64]) -> bool {
+        a.len() == b.len() && a.iter().zip(b).all(|(x, y)| (x - y).abs() < 1e-9 || (x.is_nan() && y.is_nan()))
+    }
+
+    #[test]
+    fn linear_per_year_fits_each_year_on_its_own() {
+        // 2023 rises 1 a day, 2024 rises 3 a day from a different level.
+        let dates: Vec<NaiveDate> = (360..=365).map(|d| day(2023, d)).chain((1..=5).map(|d| day(2024, d))).collect();
+        let closes: Vec<f64> = dates.iter().map(|d| if d.year() == 2023 { d.ordinal() as f64 } else { 400.0 + 3.0 * d.ordinal() as f64 }).collect();
+        let parts = decompose(&dates, &closes, DetrendMethod::LinearPerYear);
+        assert!(close_to(&parts.trend, &closes));
+        assert!(parts.residual.iter().all(|r| r.abs() < 1e-9));
+        // Jan 1 is measured on 2024's line only, not against the end of 2023's.
+        assert!(parts.trend_change[0].is_nan());
+        assert!((parts.trend_change[5] - 1.0).abs() < 1e-9);
+        assert!((parts.trend_change[6] - 3.0).abs() < 1e-9);
+        assert!((parts.trend_change[7] - 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn moving_average_shrinks_at_the_edges_and_skips_gaps() {
+        assert!(close_to(&moving_average(&[1.0, 2.0, 3.0, 4.0, 5.0], 3), &[1.5, 2.0, 3.0, 4.0, 4.5]));
+        assert!(close_to(&moving_average(&[1.0, f64::NAN, 3.0], 3), &[1.0, 2.0, 3.0]));
+        let parts = decompose(&[day(2024, 1), day(2024, 2), day(2024, 3)], &[1.0, 2.0, 6.0], DetrendMethod::MovingAverage { window: 3 });
+        assert!(close_to(&parts.trend_change, &[f64::NAN, 1.5, 1.0]));
+    }
+
+    #[test]
+    fn monthly_cycle_is_centred() {
+        let dates = [day(2024, 10), day(2024, 20), day(2024, 40), day(2024, 50)];
+        assert!(close_to(&monthly_cycle(&dates, &[1.0, 3.0, -2.0, f64::NAN]), &[2.0, 2.0, -2.0, -2.0]));
+    }
+
+    #[test]
+    fn stl_on_a_flat_series_has_no_seasonal() {
+        let dates: Vec<NaiveDate> = (1..=90).map(|d| day(2024, d)).collect();
+        let parts = decompose(&dates, &[50.0; 90], DetrendMethod::Stl { window: 21, iterations: 2 });
+        assert!(parts.trend.iter().all(|t| (t - 50.0).abs() < 1e-9));
+        assert!(parts.seasonal.iter().all(|s| s.abs() < 1e-9));
+        assert!(parts.residual.iter().all(|r| r.abs() < 1e-9));
+    }
+}
@@ -0,0 +1,185 @@
+use crate::calendar::TradingCalendar;
+use crate::model::ProcessedBar;
+use crate::processor::close_series;
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+/// Where event dates come from.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventSource {
+    /// Explicit dates, e.g. an imported list of FOMC meetings.
+    Dates { dates: Vec<String> },
+    /// Holidays of a trading calendar, optionally restricted to the named ones.
+    Calendar {
+        calendar: String,
+        #[serde(default)]
+        holidays: Option<Vec<String>>,
+    },
+}
+
+#[derive(Serialize)]
+pub struct EventPathPoint {
+    /// Sessions relative to the event; 0 is the first session on or after the event date.
+    pub offset: i32,
+    pub mean: f64,
+    pub lower: f64,
+    pub upper: f64,
+    pub observations: usize,
+}
+
+#[derive(Serialize)]
+pub struct EventStudyResult {
+    pub events_used: Vec<String>,
+    pub events_skipped: Vec<String>,
+    pub path: Vec<EventPathPoint>,
+}
+
+/// Inverse of the standard normal CDF (Acklam's rational approximation).
+pub fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [-3.969683028665376e1, 2.209460984245205e2, -2.759285104469687e2, 1.38357751867269e2, -3.066479806614716e1, 2.506628277459239];
+    const B: [f64; 5] = [-5.447609879822406e1, 1.615858368580409e2, -1.556989798598866e2, 6.680131188771972e1, -1.328068155288572e1];
+    const C: [f64; 6] = [-7.784894002430293e-3, -3.223964580411365e-1, -2.400758277161838, -2.549732539343734, 4.374664141464968, 2.938163982698783];
+    const D: [f64; 4] = [7.784695709041462e-3, 3.224671290700398e-1, 2.445134137142996, 3.754408661907416];
+    let p = p.clamp(1e-12, 1.0 - 1e-12);
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5]) / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+    if p < 0.02425 {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p > 1.0 - 0.02425 {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    }
+}
+
+/// Holiday dates of `calendar` over the given year range, optionally filtered by holiday name.
+pub fn calendar_events(calendar: &TradingCalendar, names: Option<&[String]>, first_year: i32, last_year: i32) -> Vec<NaiveDate> {
+    (first_year..=last_year)
+        .flat_map(|y| calendar.holidays_in_year(y))
+        .filter(|(_, name)| names.is_none_or(|n| n.iter().any(|x| x.eq_ignore_ascii_case(name))))
+        .map(|(d, _)| d)
+        .collect()
+}
+
+/// Years spanned by the stored rows, for expanding calendar events.
+pub fn year_span(input: &[ProcessedBar]) -> Option<(i32, i32)> {
+    let years = input.iter().filter_map(|r| r.bar.parsed_date()).map(|d| d.year());
+    let (mut lo, mut hi) = (i32::MAX, i32::MIN);
+    for y in years {
+        lo = lo.min(y);
+        hi = hi.max(y);
+    }
+    (lo <= hi).then_some((lo, hi))
+}
+
+/// Average cumulative % return from `before` sessions ahead of each event to `after` sessions past it,
+/// measured from the close of the session preceding day 0. Bands are `confidence` intervals of the mean.
+pub fn event_study(input: &[ProcessedBar], events: &[NaiveDate], before: usize, after: usize, confidence: f64) -> EventStudyResult {
+    let closes: Vec<(NaiveDate, f64)> = close_series(input).into_iter().filter(|c| c.1 != 0.0).collect();
+
+    let mut events: Vec<NaiveDate> = events.to_vec();
+    events.sort();
+    events.dedup();
+
+    let width = before + after + 1;
+    let mut paths: Vec<Vec<f64>> = Vec::new();
+    let mut events_used = Vec::new();
+    let mut events_skipped = Vec::new();
+    for event in events {
+        let day0 = closes.partition_point(|c| c.0 < event);
+        // Returns are measured from the close of the session before day 0 (`closes[day0 - 1]`), so that
+        // session must exist, as must the `before` sessions ahead of day 0 and the `after` sessions past it.
+        let label = event.format("%Y-%m-%d").to_string();
+        if day0 < before.max(1) || day0 + after >= closes.len() {
+            events_skipped.push(label);
+            continue;
+        }
+        let base = closes[day0 - 1].1;
+        paths.push(
+            (day0 - before..=day0 + after)
+                .map(|i| (closes[i].1 / base - 1.0) * 100.0)
+                .collect(),
+        );
+        events_used.push(label);
+    }
+
+    let z = normal_quantile(0.5 + confidence.clamp(0.0, 0.999) / 2.0);
+    let path = (0..width)
+        .filter(|_| !paths.is_empty())
+        .map(|k| {
+            let values: Vec<f64> = paths.iter().map(|p| p[k]).collect();
+            let n = values.len() as f64;
+            let mean = values.iter().sum::<f64>() / n;
+            let std = if values.len() > 1 {
+                (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt()
+            } else {
+                0.0
+            };
+            let half = z * std / n.sqrt();
+            EventPathPoint {
+                offset: k as i32 - before as i32,
+                mean,
+                lower: mean - half,
+                upper: mean + half,
+                observations: values.len(),
+            }
+        })
+        .collect();
+
+    EventStudyResult { events_used, events_skipped, path }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::test_bar;
+
+    fn bars(closes: &[f64]) -> Vec<ProcessedBar> {
+        closes.iter().enumerate().map(|(i, c)| test_bar(&format!("2024-01-{:02}", i + 1), *c)).collect()
+    }
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, d).unwrap()
+    }
+
+    #[test]
+    fn returns_are_measured_from_the_session_before_day_zero() {
+        let result = event_study(&bars(&[100.0, 110.0, 121.0, 100.0, 50.0]), &[day(3)], 1, 1, 0.95);
+        assert_eq!(result.events_used, vec!["2024-01-03"]);
+        let means: Vec<f64> = result.path.iter().map(|p| p.mean).collect();
+        let expected = [0.0, 10.0, -100.0 / 11.0];
+        for (m, e) in means.iter().zip(expected) {
+            assert!((m - e).abs() < 1e-9, "{:?}", means);
+        }
+        assert_eq!(result.path[0].offset, -1);
+    }
+
+    #[test]
+    fn events_without_enough_history_are_skipped() {
+        let input = bars(&[100.0, 110.0, 121.0, 100.0]);
+        // Day 0 is the first session: there is no close before it.
+        assert_eq!(event_study(&input, &[day(1)], 0, 1, 0.95).events_skipped, vec!["2024-01-01"]);
+        // Two sessions ahead of day 0 are available, and the base is the second of them.
+        assert_eq!(event_study(&input, &[day(3)], 2, 1, 0.95).events_used, vec!["2024-01-03"]);
+        assert_eq!(event_study(&input, &[day(3)], 3, 1, 0.95).events_skipped, vec!["2024-01-03"]);
+        assert_eq!(event_study(&input, &[day(3)], 1, 2, 0.95).events_skipped, vec!["2024-01-03"]);
+    }
+
+    #[test]
+    fn intraday_bars_are_measured_from_the_last_bar_of_each_day() {
+        let input = vec![
+            test_bar("2024-01-01T10:00:00", 90.0),
+            test_bar("2024-01-01T16:00:00", 100.0),
+            test_bar("2024-01-02T10:00:00", 130.0),
+            test_bar("2024-01-02T16:00:00", 110.0),
+        ];
+        let result = event_study(&input, &[day(2)], 0, 0, 0.95);
+        assert_eq!(result.events_used, vec!["2024-01-02"]);
+        assert!((result.path[0].mean - 10.0).abs() < 1e-9, "{}", result.path[0].mean);
+    }
+}
@@ -0,0 +1,231 @@
+use crate::model::{OhlcBar, ProcessedBar};
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// How missing sessions are treated before derived columns are computed.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FillPolicy {
+    /// Keep the data as imported.
+    #[default]
+    LeaveGap,
+    /// Insert flat bars at the previous close.
+    ForwardFill,
+    /// Insert bars linearly interpolated between the surrounding sessions.
+    Interpolate,
+}
+
+impl FillPolicy {
+    pub fn describe(self) -> &'static str {
+        match self {
+            FillPolicy::LeaveGap => "Leave gaps",
+            FillPolicy::ForwardFill => "Forward-fill at previous close",
+            FillPolicy::Interpolate => "Linear interpolation",
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Gap {
+    pub after: String,
+    pub before: String,
+    pub missing_sessions: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct GapReport {
+    /// Sessions from the first to the last stored date under the calendar.
+    pub expected_sessions: usize,
+    /// Expected sessions with at least one bar.
+    pub present_sessions: usize,
+    pub missing_sessions: usize,
+    pub gaps: Vec<Gap>,
+    /// Dates (timestamps for intraday data) stored on more than one bar.
+    pub duplicates: Vec<String>,
+}
+
+/// Marker column on bars inserted by `fill_gaps`.
+const FILLED: &str = "Filled";
+
+fn is_filled(bar: &OhlcBar) -> bool {
+    bar.extra.get(FILLED) == Some(&Value::Bool(true))
+}
+
+/// Fallback calendar: every weekday is a session.
+pub fn is_weekday(date: NaiveDate) -> bool {
+    !matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+}
+
+/// Daily rows keyed by date, sorted; bars sharing a date are all kept. Intraday data is not gap-filled,
+/// so `None` is returned for it.
+fn daily_rows(input: &[OhlcBar]) -> Option<Vec<(NaiveDate, &OhlcBar)>> {
+    let mut rows = Vec::with_capacity(input.len());
+    for row in input {
+        let Some(ts) = row.timestamp() else { continue };
+        if ts.intraday {
+            return None;
+        }
+        rows.push((ts.date(), row));
+    }
+    rows.sort_by_key(|r| r.0);
+    Some(rows)
+}
+
+fn sessions_between(from: NaiveDate, to: NaiveDate, is_session: &dyn Fn(NaiveDate) -> bool) -> Vec<NaiveDate> {
+    let mut out = Vec::new();
+    let mut d = from + Duration::days(1);
+    while d < to {
+        if is_session(d) {
+            out.push(d);
+        }
+        d += Duration::days(1);
+    }
+    out
+}
+
+/// Sessions without any imported bar between the first and last stored date; bars inserted by `fill_gaps`
+/// do not count, so filled gaps are still reported. Intraday bars count towards the session (day) they fall on.
+pub fn detect_gaps(input: &[ProcessedBar], is_session: &dyn Fn(NaiveDate) -> bool) -> GapReport {
+    let imported: Vec<&OhlcBar> = input.iter().map(|r| &r.bar).filter(|b| !is_filled(b)).collect();
+    let dates: Vec<NaiveDate> = imported.iter().filter_map(|b| b.parsed_date()).collect::<BTreeSet<_>>().into_iter().collect();
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for ts in imported.iter().filter_map(|b| b.timestamp()) {
+        *counts.entry(ts.format()).or_default() += 1;
+    }
+    let duplicates = counts.into_iter().filter(|(_, n)| *n > 1).map(|(d, _)| d).collect();
+
+    let mut gaps = Vec::new();
+    let mut missing = 0;
+    for pair in dates.windows(2) {
+        let (from, to) = (pair[0], pair[1]);
+        let sessions = sessions_between(from, to, is_session);
+        if sessions.is_empty() {
+            continue;
+        }
+        missing += sessions.len();
+        gaps.push(Gap {
+            after: from.format("%Y-%m-%d").to_string(),
+            before: to.format("%Y-%m-%d").to_string(),
+            missing_sessions: sessions.iter().map(|d| d.format("%Y-%m-%d").to_string()).collect(),
+        });
+    }
+    let present = dates.iter().filter(|d| is_session(**d)).count();
+    GapReport {
+        expected_sessions: present + missing,
+        present_sessions: present,
+        missing_sessions: missing,
+        gaps,
+        duplicates,
+    }
+}
+
+/// Inserts synthetic bars for missing sessions according to `policy`. Synthetic rows carry
+/// `"Filled": true` so they can be told apart after storage; rows filled by an earlier run are
+/// dropped first, so refilling stored data follows the current policy and neighbours.
+pub fn fill_gaps(input: &[OhlcBar], policy: FillPolicy, is_session: &dyn Fn(NaiveDate) -> bool) -> Vec<OhlcBar> {
+    let input: Vec<OhlcBar> = input.iter().filter(|b| !is_filled(b)).cloned().collect();
+    if policy == FillPolicy::LeaveGap {
+        return input;
+    }
+    let Some(rows) = daily_rows(&input) else {
+        return input;
+    };
+
+    let mut out: Vec<OhlcBar> = Vec::with_capacity(rows.len());
+    for (i, (date, row)) in rows.iter().enumerate() {
+        if let Some((prev_date, prev)) = i.checked_sub(1).map(|p| rows[p]) {
+            let sessions = sessions_between(prev_date, *date, is_session);
+            let span = (*date - prev_date).num_days() as f64;
+            for d in sessions {
+                let t = (d - prev_date).num_days() as f64 / span;
+                let fill = |a: Option<f64>, b: Option<f64>| match policy {
+                    FillPolicy::ForwardFill => prev.close,
+                    _ => Some(a? + (b? - a?) * t),
+                };
+                out.push(OhlcBar {
+                    date: Some(d.format("%Y-%m-%d").to_string()),
+                    open: fill(prev.open, row.open),
+                    high: fill(prev.high, row.high),
+                    low: fill(prev.low, row.low),
+                    close: fill(prev.close, row.close),
+                    extra: Map::from_iter([(FILLED.to_string(), Value::Bool(true))]),
+                    ..Default::default()
+                });
+            }
+        }
+        out.push((*row).clone());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::test_bar;
+
+    fn bar(date: &str, close: f64) -> OhlcBar {
+        test_bar(date, close).bar
+    }
+
+    fn stored(bars: &[OhlcBar]) -> Vec<ProcessedBar> {
+        bars.iter().map(|b| ProcessedBar { bar: b.clone(), ..Default::default() }).collect()
+    }
+
+    #[test]
+    fn counts_sessions_from_the_calendar() {
+        // Fri 5th, then Wed 10th: Mon 8th and Tue 9th are missing, the weekend is not a session.
+        let report = detect_gaps(&stored(&[bar("2024-01-05", 1.0), bar("2024-01-10", 2.0), bar("2024-01-13", 3.0)]), &is_weekday);
+        assert_eq!(report.missing_sessions, 4);
+        assert_eq!(report.present_sessions, 2);
+        assert_eq!(report.expected_sessions, 6);
+        assert_eq!(report.gaps[0].missing_sessions, vec!["2024-01-08", "2024-01-09"]);
+    }
+
+    #[test]
+    fn intraday_bars_are_checked_per_day() {
+        let input = [bar("2024-01-08T09:30:00", 1.0), bar("2024-01-08T16:00:00", 1.0), bar("2024-01-10T09:30:00", 2.0)];
+        let report = detect_gaps(&stored(&input), &is_weekday);
+        assert_eq!(report.present_sessions, 2);
+        assert_eq!(report.gaps[0].missing_sessions, vec!["2024-01-09"]);
+    }
+
+    #[test]
+    fn interpolates_and_refills_from_scratch() {
+        let input = [bar("2024-01-08", 10.0), bar("2024-01-11", 40.0)];
+        let filled = fill_gaps(&input, FillPolicy::Interpolate, &is_weekday);
+        let closes: Vec<Option<f64>> = filled.iter().map(|b| b.close).collect();
+        assert_eq!(closes, vec![Some(10.0), Some(20.0), Some(30.0), Some(40.0)]);
+        assert_eq!(filled[1].extra.get(FILLED), Some(&Value::Bool(true)));
+
+        assert_eq!(fill_gaps(&filled, FillPolicy::Interpolate, &is_weekday), filled);
+        assert_eq!(fill_gaps(&filled, FillPolicy::LeaveGap, &is_weekday), input);
+        let forward = fill_gaps(&filled, FillPolicy::ForwardFill, &is_weekday);
+        assert_eq!(forward[2].close, Some(10.0));
+    }
+
+    #[test]
+    fn filled_sessions_are_still_reported() {
+        let filled = fill_gaps(&[bar("2024-01-08", 10.0), bar("2024-01-11", 40.0)], FillPolicy::Interpolate, &is_weekday);
+        let report = detect_gaps(&stored(&filled), &is_weekday);
+        assert_eq!(report.present_sessions, 2);
+        assert_eq!(report.gaps[0].missing_sessions, vec!["2024-01-09", "2024-01-10"]);
+    }
+
+    #[test]
+    fn duplicate_dates_are_reported_and_kept() {
+        let input = [bar("2024-01-08", 10.0), bar("2024-01-08", 11.0), bar("2024-01-10", 30.0)];
+        let report = detect_gaps(&stored(&input), &is_weekday);
+        assert_eq!(report.duplicates, vec!["2024-01-08"]);
+        assert_eq!(report.present_sessions, 2);
+
+        let filled = fill_gaps(&input, FillPolicy::Interpolate, &is_weekday);
+        let closes: Vec<Option<f64>> = filled.iter().map(|b| b.close).collect();
+        // The gap is bridged from the last bar of the duplicated date.
+        assert_eq!(closes, vec![Some(10.0), Some(11.0), Some(20.5), Some(30.0)]);
+
+        let intraday = [bar("2024-01-08T09:30:00", 1.0), bar("2024-01-08T16:00:00", 1.0), bar("2024-01-08T16:00:00", 2.0)];
+        assert_eq!(detect_gaps(&stored(&intraday), &is_weekday).duplicates, vec!["2024-01-08T16:00:00"]);
+    }
+}
@@ -0,0 +1,291 @@
+use crate::model::ProcessedBar;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Optional technical indicators that can be computed alongside the seasonal columns.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IndicatorSpec {
+    Sma { period: usize },
+    Ema { period: usize },
+    Rsi { period: usize },
+    Atr { period: usize },
+    Bollinger { period: usize, std_dev: f64 },
+    Macd { fast: usize, slow: usize, signal: usize },
+    Volatility { period: usize },
+}
+
+impl IndicatorSpec {
+    /// Output column names, in the order they are written.
+    pub fn columns(&self) -> Vec<String> {
+        match self {
+            IndicatorSpec::Sma { period } => vec![format!("SMA_{}", period)],
+            IndicatorSpec::Ema { period } => vec![format!("EMA_{}", period)],
+            IndicatorSpec::Rsi { period } => vec![format!("RSI_{}", period)],
+            IndicatorSpec::Atr { period } => vec![format!("ATR_{}", period)],
+            IndicatorSpec::Bollinger { period, std_dev } => vec![
+                format!("BB_Upper_{}_{}", period, std_dev),
+                format!("BB_Middle_{}_{}", period, std_dev),
+                format!("BB_Lower_{}_{}", period, std_dev),
+            ],
+            IndicatorSpec::Macd { fast, slow, signal } => vec![
+                format!("MACD_{}_{}_{}", fast, slow, signal),
+                format!("MACD_Signal_{}_{}_{}", fast, slow, signal),
+                format!("MACD_Hist_{}_{}_{}", fast, slow, signal),
+            ],
+            IndicatorSpec::Volatility { period } => vec![format!("Volatility_{}", period)],
+        }
+    }
+}
+
+pub fn column_names(specs: &[IndicatorSpec]) -> Vec<String> {
+    specs.iter().flat_map(|s| s.columns()).collect()
+}
+
+fn sma(values: &[Option<f64>], period: usize) -> Vec<Option<f64>> {
+    let mut out = vec![None; values.len()];
+    if period == 0 {
+        return out;
+    }
+    for i in (period - 1)..values.len() {
+        let window = &values[i + 1 - period..=i];
+        if window.iter().all(|v| v.is_some()) {
+            out[i] = Some(window.iter().flatten().sum::<f64>() / period as f64);
+        }
+    }
+    out
+}
+
+/// EMA seeded with the SMA of the first `period` values.
+fn ema(values: &[Option<f64>], period: usize) -> Vec<Option<f64>> {
+    let mut out = vec![None; values.len()];
+    if period == 0 {
+        return out;
+    }
+    let alpha = 2.0 / (period as f64 + 1.0);
+    let mut prev: Option<f64> = None;
+    let mut seed: Vec<f64> = Vec::with_capacity(period);
+    for (i, v) in values.iter().enumerate() {
+        let Some(v) = v else { continue };
+        match prev {
+            Some(p) => {
+                let next = alpha * v + (1.0 - alpha) * p;
+                out[i] = Some(next);
+                prev = Some(next);
+            }
+            None => {
+                seed.push(*v);
+                if seed.len() == period {
+                    let s = seed.iter().sum::<f64>() / period as f64;
+                    out[i] = Some(s);
+                    prev = Some(s);
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Wilder smoothing: seed with the simple mean, then `prev + (x - prev) / period`.
+fn wilder(values: &[Option<f64>], period: usize) -> Vec<Option<f64>> {
+    let mut out = vec![None; values.len()];
+    if period == 0 {
+        return out;
+    }
+    let mut prev: Option<f64> = None;
+    let mut seed: Vec<f64> = Vec::with_capacity(period);
+    for (i, v) in values.iter().enumerate() {
+        let Some(v) = v else { continue };
+        match prev {
+            Some(p) => {
+                let next = p + (v - p) / period as f64;
+                out[i] = Some(next);
+                prev = Some(next);
+            }
+            None => {
+                seed.push(*v);
+                if seed.len() == period {
+                    let s = seed.iter().sum::<f64>() / period as f64;
+                    out[i] = Some(s);
+                    prev = Some(s);
+                }
+            }
+        }
+    }
+    out
+}
+
+fn rolling_std(values: &[Option<f64>], period: usize) -> Vec<Option<f64>> {
+    let mut out = vec![None; values.len()];
+    if period < 2 {
+        return out;
+    }
+    for i in (period - 1)..values.len() {
+        let window: Vec<f64> = values[i + 1 - period..=i].iter().flatten().copied().collect();
+        if window.len() == period {
+            let mean = window.iter().sum::<f64>() / period as f64;
+            let var = window.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / period as f64;
+            out[i] = Some(var.sqrt());
+        }
+    }
+    out
+}
+
+fn rsi(closes: &[Option<f64>], period: usize) -> Vec<Option<f64>> {
+    let mut gains = vec![None; closes.len()];
+    let mut losses = vec![None; closes.len()];
+    for i in 1..closes.len() {
+        if let (Some(prev), Some(cur)) = (closes[i - 1], closes[i]) {
+            let change = cur - prev;
+            gains[i] = Some(change.max(0.0));
+            losses[i] = Some((-change).max(0.0));
+        }
+    }
+    let avg_gain = wilder(&gains, period);
+    let avg_loss = wilder(&losses, period);
+    avg_gain
+        .iter()
+        .zip(&avg_loss)
+        .map(|(g, l)| match (g, l) {
+            (Some(g), Some(l)) if *l == 0.0 => Some(if *g == 0.0 { 50.0 } else { 100.0 }),
+            (Some(g), Some(l)) => Some(100.0 - 100.0 / (1.0 + g / l)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn atr(highs: &[Option<f64>], lows: &[Option<f64>], closes: &[Option<f64>], period: usize) -> Vec<Option<f64>> {
+    let true_range: Vec<Option<f64>> = (0..closes.len())
+        .map(|i| {
+            let (h, l) = (highs[i]?, lows[i]?);
+            let range = h - l;
+            match i.checked_sub(1).and_then(|p| closes[p]) {
+                Some(prev) => Some(range.max((h - prev).abs()).max((l - prev).abs())),
+                None => Some(range),
+            }
+        })
+        .collect();
+    wilder(&true_range, period)
+}
+
+/// Annualized (252 sessions) rolling standard deviation of log returns, in percent.
+fn volatility(closes: &[Option<f64>], period: usize) -> Vec<Option<f64>> {
+    let mut log_returns = vec![None; closes.len()];
+    for i in 1..closes.len() {
+        if let (Some(prev), Some(cur)) = (closes[i - 1], closes[i]) {
+            if prev > 0.0 && cur > 0.0 {
+                log_returns[i] = Some((cur / prev).ln());
+            }
+        }
+    }
+    rolling_std(&log_returns, period)
+        .into_iter()
+        .map(|v| v.map(|s| s * 252f64.sqrt() * 100.0))
+        .collect()
+}
+
+fn compute(spec: &IndicatorSpec, highs: &[Option<f64>], lows: &[Option<f64>], closes: &[Option<f64>]) -> Vec<Vec<Option<f64>>> {
+    match spec {
+        IndicatorSpec::Sma { period } => vec![sma(closes, *period)],
+        IndicatorSpec::Ema { period } => vec![ema(closes, *period)],
+        IndicatorSpec::Rsi { period } => vec![rsi(closes, *period)],
+        IndicatorSpec::Atr { period } => vec![atr(highs, lows, closes, *period)],
+        IndicatorSpec::Bollinger { period, std_dev } => {
+            let middle = sma(closes, *period);
+            let std = rolling_std(closes, *period);
+            let band = |sign: f64| -> Vec<Option<f64>> {
+                middle
+                    .iter()
+                    .zip(&std)
+                    .map(|(m, s)| Some((*m)? + sign * std_dev * (*s)?))
+                    .collect()
+            };
+            vec![band(1.0), middle.clone(), band(-1.0)]
+        }
+        IndicatorSpec::Macd { fast, slow, signal } => {
+            let fast_ema = ema(closes, *fast);
+            let slow_ema = ema(closes, *slow);
+            let macd: Vec<Option<f64>> = fast_ema.iter().zip(&slow_ema).map(|(f, s)| Some((*f)? - (*s)?)).collect();
+            let signal_line = ema(&macd, *signal);
+            let hist: Vec<Option<f64>> = macd.iter().zip(&signal_line).map(|(m, s)| Some((*m)? - (*s)?)).collect();
+            vec![macd, signal_line, hist]
+        }
+        IndicatorSpec::Volatility { period } => vec![volatility(closes, *period)],
+    }
+}
+
+/// Removes the columns `specs` computed, e.g. before rows are stored with a different indicator set. Source
+/// columns that merely look like indicators (an imported `SMA_50`) are left alone.
+pub fn remove_indicators(rows: &mut [ProcessedBar], specs: &[IndicatorSpec]) {
+    let names = column_names(specs);
+    for row in rows.iter_mut() {
+        row.bar.extra.retain(|k, _| !names.contains(k));
+    }
+}
+
+/// Sets the selected indicator columns on date-sorted processed rows. Warm-up rows get `null`.
+pub fn apply_indicators(rows: &mut [ProcessedBar], specs: &[IndicatorSpec]) {
+    let highs: Vec<Option<f64>> = rows.iter().map(|r| r.bar.high).collect();
+    let lows: Vec<Option<f64>> = rows.iter().map(|r| r.bar.low).collect();
+    let closes: Vec<Option<f64>> = rows.iter().map(|r| r.bar.close).collect();
+
+    for spec in specs {
+        for (name, values) in spec.columns().into_iter().zip(compute(spec, &highs, &lows, &closes)) {
+            for (row, v) in rows.iter_mut().zip(values) {
+                row.bar.extra.insert(
+                    name.clone(),
+                    v.and_then(serde_json::Number::from_f64).map(Value::Number).unwrap_or(Value::Null),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::test_bar;
+
+    fn rows(closes: &[f64]) -> Vec<ProcessedBar> {
+        closes.iter().enumerate().map(|(i, c)| test_bar(&format!("2024-01-{:02}", i + 1), *c)).collect()
+    }
+
+    fn column(rows: &[ProcessedBar], name: &str) -> Vec<Option<f64>> {
+        rows.iter().map(|r| r.bar.extra.get(name).and_then(|v| v.as_f64())).collect()
+    }
+
+    #[test]
+    fn sma_has_a_warm_up() {
+        let mut rows = rows(&[1.0, 2.0, 3.0, 4.0]);
+        apply_indicators(&mut rows, &[IndicatorSpec::Sma { period: 3 }]);
+        assert_eq!(column(&rows, "SMA_3"), vec![None, None, Some(2.0), Some(3.0)]);
+    }
+
+    #[test]
+    fn ema_is_seeded_with_the_sma() {
+        let mut rows = rows(&[1.0, 2.0, 3.0, 4.0]);
+        apply_indicators(&mut rows, &[IndicatorSpec::Ema { period: 3 }]);
+        assert_eq!(column(&rows, "EMA_3"), vec![None, None, Some(2.0), Some(3.0)]);
+    }
+
+    #[test]
+    fn recompute_drops_only_columns_of_removed_specs() {
+        let mut rows = rows(&[1.0, 2.0, 3.0, 4.0]);
+        rows[0].bar.extra.insert("Note".to_string(), Value::from("kept"));
+        // An imported column with an indicator-like name is source data.
+        rows[0].bar.extra.insert("SMA_50".to_string(), Value::from(7.5));
+        let previous = [IndicatorSpec::Sma { period: 2 }, IndicatorSpec::Rsi { period: 2 }];
+        apply_indicators(&mut rows, &previous);
+        remove_indicators(&mut rows, &previous);
+        apply_indicators(&mut rows, &[IndicatorSpec::Sma { period: 3 }]);
+        let keys: Vec<&String> = rows[0].bar.extra.keys().collect();
+        assert_eq!(keys, vec!["Note", "SMA_3", "SMA_50"]);
+        assert_eq!(rows[0].bar.extra["SMA_50"], Value::from(7.5));
+    }
+
+    #[test]
+    fn column_names_follow_the_spec_order() {
+        let specs = [IndicatorSpec::Rsi { period: 14 }, IndicatorSpec::Bollinger { period: 20, std_dev: 2.5 }];
+        assert_eq!(column_names(&specs), vec!["RSI_14", "BB_Upper_20_2.5", "BB_Middle_20_2.5", "BB_Lower_20_2.5"]);
+    }
+}
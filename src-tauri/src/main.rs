@@ -3,9 +3,24 @@
     windows_subsystem = "windows"
 )]
 
+mod adjustments;
+mod backtest;
+mod calendar;
 mod commands;
+mod correlation;
+mod cycles;
 mod db;
+mod detrend;
+mod events;
+mod gaps;
+mod indicators;
+mod model;
+mod outliers;
 mod processor;
+mod risk;
+mod rolling;
+mod screener;
+mod synthetic;
 
 use db::DbState;
 use std::sync::Mutex;
@@ -16,6 +31,7 @@ fn main() {
         .setup(|app| {
             let db_path = db::default_db_path(app)?;
             db::ensure_schema(&db_path)?;
+            calendar::ensure_user_calendars(&calendar::calendars_path(&db_path))?;
 
             app.manage(DbState {
                 db_path,
@@ -35,6 +51,32 @@ fn main() {
             commands::get_asset_data,
             commands::get_database_stats,
             commands::get_asset_date_range,
+            commands::find_analog_years,
+            commands::get_seasonal_forecast,
+            commands::get_seasonal_bands,
+            commands::get_filtered_seasonality,
+            commands::run_seasonal_screener,
+            commands::find_best_windows,
+            commands::run_backtest,
+            commands::get_volume_seasonality,
+            commands::get_risk_seasonality,
+            commands::export_seasonal_summary,
+            commands::resample_asset,
+            commands::get_intraday_seasonality,
+            commands::detect_gaps,
+            commands::get_calendars,
+            commands::save_calendar,
+            commands::set_asset_calendar,
+            commands::get_holiday_effects,
+            commands::run_event_study,
+            commands::get_corporate_actions,
+            commands::save_corporate_actions,
+            commands::build_continuous_futures,
+            commands::get_roll_dates,
+            commands::define_synthetic_asset,
+            commands::get_synthetic_definition,
+            commands::get_correlation_matrix,
+            commands::export_correlation_excel,
             commands::add_row,
             commands::update_row,
             commands::delete_row,
@@ -0,0 +1,298 @@
+use crate::processor::{as_f64, parse_timestamp, Timestamp};
+use chrono::NaiveDate;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+
+/// A price bar as imported or entered, before anything is derived from it. Header variants seen in
+/// source files (`Vol`, `Open Interest`, ...) are mapped to the canonical name by [`OhlcBar::from_map`].
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct OhlcBar {
+    #[serde(rename = "Date", default, deserialize_with = "lenient_string")]
+    pub date: Option<String>,
+    #[serde(rename = "Open", default, deserialize_with = "lenient_f64")]
+    pub open: Option<f64>,
+    #[serde(rename = "High", default, deserialize_with = "lenient_f64")]
+    pub high: Option<f64>,
+    #[serde(rename = "Low", default, deserialize_with = "lenient_f64")]
+    pub low: Option<f64>,
+    #[serde(rename = "Close", default, deserialize_with = "lenient_f64")]
+    pub close: Option<f64>,
+    #[serde(rename = "Volume", default, deserialize_with = "lenient_f64", skip_serializing_if = "Option::is_none")]
+    pub volume: Option<f64>,
+    #[serde(rename = "Open_Interest", default, deserialize_with = "lenient_f64", skip_serializing_if = "Option::is_none")]
+    pub open_interest: Option<f64>,
+    /// Source columns outside the known set (notes, tags, raw prices, indicator values), kept as-is.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A bar with the columns the app derives and stores for it. Serializes to the flat row the frontend
+/// and the Excel export see.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ProcessedBar {
+    #[serde(default, deserialize_with = "lenient_i64", skip_serializing_if = "Option::is_none")]
+    pub id: Option<i64>,
+    #[serde(flatten)]
+    pub bar: OhlcBar,
+    #[serde(rename = "%change", default, deserialize_with = "lenient_f64")]
+    pub pct_change: Option<f64>,
+    #[serde(rename = "M-no", default, deserialize_with = "lenient_i64")]
+    pub month_no: Option<i64>,
+    /// Session number within the year under the asset's trading calendar.
+    #[serde(rename = "TD-no", default, deserialize_with = "lenient_i64", skip_serializing_if = "Option::is_none")]
+    pub trading_day_no: Option<i64>,
+    #[serde(default, deserialize_with = "lenient_f64")]
+    pub normalized: Option<f64>,
+    #[serde(rename = "Average_Norm", default, deserialize_with = "lenient_f64")]
+    pub average_norm: Option<f64>,
+    #[serde(rename = "True_Seasonal", default, deserialize_with = "lenient_f64")]
+    pub true_seasonal: Option<f64>,
+    #[serde(rename = "Outlier", default, deserialize_with = "lenient_string", skip_serializing_if = "Option::is_none")]
+    pub outlier: Option<String>,
+    #[serde(rename = "Trend", default, deserialize_with = "lenient_f64", skip_serializing_if = "Option::is_none")]
+    pub trend: Option<f64>,
+    #[serde(rename = "Seasonal", default, deserialize_with = "lenient_f64", skip_serializing_if = "Option::is_none")]
+    pub seasonal: Option<f64>,
+    #[serde(rename = "Residual", default, deserialize_with = "lenient_f64", skip_serializing_if = "Option::is_none")]
+    pub residual: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub processed_date: Option<String>,
+}
+
+/// Source header variants and the column they are read into.
+const HEADER_ALIASES: [(&str, &str); 4] = [
+    ("Vol", "Volume"),
+    ("Open Interest", "Open_Interest"),
+    ("OpenInterest", "Open_Interest"),
+    ("OI", "Open_Interest"),
+];
+
+fn is_blank(v: &Value) -> bool {
+    match v {
+        Value::Null => true,
+        Value::String(s) => s.trim().is_empty(),
+        _ => false,
+    }
+}
+
+impl OhlcBar {
+    /// Reads a source row. Header variants are renamed to their column first; when a row has several
+    /// (say `Vol` and `Volume`), the canonical column wins unless it is empty, then the first non-empty variant.
+    pub fn from_map(row: &Map<String, Value>) -> serde_json::Result<Self> {
+        let mut row = row.clone();
+        for (alias, column) in HEADER_ALIASES {
+            let Some(value) = row.remove(alias) else { continue };
+            if row.get(column).is_none_or(is_blank) {
+                row.insert(column.to_string(), value);
+            }
+        }
+        serde_json::from_value(Value::Object(row))
+    }
+
+    pub fn to_map(&self) -> Map<String, Value> {
+        to_map(self)
+    }
+
+    pub(crate) fn timestamp(&self) -> Option<Timestamp> {
+        parse_timestamp(&Value::String(self.date.clone()?))
+    }
+
+    pub fn parsed_date(&self) -> Option<NaiveDate> {
+        self.timestamp().map(|t| t.date())
+    }
+
+    /// Drops columns of the processed model that ended up in `extra`, e.g. from re-importing an export.
+    pub fn without_derived(mut self) -> Self {
+        self.extra.retain(|k, _| k != "id" && !COLUMNS.iter().any(|(name, _)| name == k));
+        self
+    }
+}
+
+impl ProcessedBar {
+    /// Reads a flat row; keys that are not columns of the model end up in `bar.extra`.
+    pub fn from_map(row: &Map<String, Value>) -> serde_json::Result<Self> {
+        serde_json::from_value(Value::Object(row.clone()))
+    }
+
+    pub fn to_map(&self) -> Map<String, Value> {
+        to_map(self)
+    }
+}
+
+fn to_map<T: Serialize>(value: &T) -> Map<String, Value> {
+    match serde_json::to_value(value) {
+        Ok(Value::Object(m)) => m,
+        _ => Map::new(),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColumnType {
+    Real,
+    Integer,
+    Text,
+    /// A JSON object in a TEXT column, flattened into the row on read.
+    Json,
+}
+
+impl ColumnType {
+    pub fn sql(self) -> &'static str {
+        match self {
+            ColumnType::Real => "REAL",
+            ColumnType::Integer => "INTEGER",
+            ColumnType::Text | ColumnType::Json => "TEXT",
+        }
+    }
+}
+
+/// `asset_data` columns after `id`, in table order. Names are the serde names on `ProcessedBar`; the
+/// schema, inserts, reads and the Excel column order all follow this list.
+pub const COLUMNS: [(&str, ColumnType); 21] = [
+    ("Date", ColumnType::Text),
+    ("Open", ColumnType::Real),
+    ("High", ColumnType::Real),
+    ("Low", ColumnType::Real),
+    ("Close", ColumnType::Real),
+    ("Volume", ColumnType::Real),
+    ("Open_Interest", ColumnType::Real),
+    ("%change", ColumnType::Real),
+    ("M-no", ColumnType::Integer),
+    ("TD-no", ColumnType::Integer),
+    ("normalized", ColumnType::Real),
+    ("Average_Norm", ColumnType::Real),
+    ("True_Seasonal", ColumnType::Real),
+    ("Outlier", ColumnType::Text),
+    ("Trend", ColumnType::Real),
+    ("Seasonal", ColumnType::Real),
+    ("Residual", ColumnType::Real),
+    ("asset", ColumnType::Text),
+    ("processed_date", ColumnType::Text),
+    ("indicators", ColumnType::Json),
+    ("extra", ColumnType::Json),
+];
+
+/// Bookkeeping columns that are stored but not part of a bar's data.
+const METADATA_COLUMNS: [&str; 2] = ["asset", "processed_date"];
+
+/// Columns written first, in this order, when rows are exported.
+pub fn export_columns() -> impl Iterator<Item = &'static str> {
+    COLUMNS
+        .iter()
+        .filter(|(name, kind)| *kind != ColumnType::Json && !METADATA_COLUMNS.contains(name))
+        .map(|(name, _)| *name)
+}
+
+/// Numbers may arrive as text from spreadsheets; anything unreadable or non-finite becomes `None`.
+fn lenient_f64<'de, D: Deserializer<'de>>(d: D) -> Result<Option<f64>, D::Error> {
+    Ok(Option::<Value>::deserialize(d)?.as_ref().and_then(as_f64).filter(|v| v.is_finite()))
+}
+
+fn lenient_i64<'de, D: Deserializer<'de>>(d: D) -> Result<Option<i64>, D::Error> {
+    Ok(Option::<Value>::deserialize(d)?
+        .as_ref()
+        .and_then(|v| v.as_i64().or_else(|| as_f64(v).filter(|f| f.is_finite()).map(|f| f.round() as i64))))
+}
+
+/// Text columns keep strings as given; numbers (e.g. Excel date serials) are kept in their text form.
+fn lenient_string<'de, D: Deserializer<'de>>(d: D) -> Result<Option<String>, D::Error> {
+    Ok(match Option::<Value>::deserialize(d)? {
+        None | Some(Value::Null) => None,
+        Some(Value::String(s)) => Some(s),
+        Some(other) => Some(other.to_string()),
+    })
+}
+
+/// Test fixture: a bar on `date` with open, high, low and close all at `close`.
+#[cfg(test)]
+pub(crate) fn test_bar(date: &str, close: f64) -> ProcessedBar {
+    ProcessedBar {
+        bar: OhlcBar {
+            date: Some(date.to_string()),
+            open: Some(close),
+            high: Some(close),
+            low: Some(close),
+            close: Some(close),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn full_bar() -> ProcessedBar {
+        ProcessedBar {
+            id: Some(7),
+            bar: OhlcBar {
+                date: Some("2024-03-01".to_string()),
+                open: Some(1.0),
+                high: Some(2.0),
+                low: Some(0.5),
+                close: Some(1.5),
+                volume: Some(100.0),
+                open_interest: Some(50.0),
+                extra: Map::from_iter([("Note".to_string(), json!("roll week"))]),
+            },
+            pct_change: Some(0.25),
+            month_no: Some(3),
+            trading_day_no: Some(41),
+            normalized: Some(12.5),
+            average_norm: Some(10.0),
+            true_seasonal: Some(55.0),
+            outlier: Some("spike".to_string()),
+            trend: Some(1.4),
+            seasonal: Some(0.1),
+            residual: Some(0.0),
+            asset: Some("CL".to_string()),
+            processed_date: Some("2024-03-02T00:00:00".to_string()),
+        }
+    }
+
+    #[test]
+    fn columns_match_serialized_keys() {
+        let mut keys: Vec<String> = full_bar().to_map().into_iter().map(|(k, _)| k).filter(|k| k != "id" && k != "Note").collect();
+        let mut columns: Vec<String> = COLUMNS.iter().filter(|(_, kind)| *kind != ColumnType::Json).map(|(n, _)| n.to_string()).collect();
+        keys.sort();
+        columns.sort();
+        assert_eq!(keys, columns);
+    }
+
+    #[test]
+    fn processed_bar_round_trips_through_a_row() {
+        let bar = full_bar();
+        assert_eq!(ProcessedBar::from_map(&bar.to_map()).unwrap(), bar);
+    }
+
+    #[test]
+    fn unset_optional_columns_are_not_serialized() {
+        let row = ProcessedBar::default().to_map();
+        for key in ["id", "Volume", "Open_Interest", "TD-no", "Outlier", "Trend", "asset"] {
+            assert!(!row.contains_key(key), "{} should be skipped", key);
+        }
+    }
+
+    #[test]
+    fn header_aliases_do_not_clash_with_canonical_columns() {
+        let row = json!({ "Date": "2024-01-02", "Close": "3.5", "Vol": 10, "Volume": null, "OI": 4, "Open Interest": 5 });
+        let bar = OhlcBar::from_map(row.as_object().unwrap()).unwrap();
+        assert_eq!(bar.volume, Some(10.0));
+        assert_eq!(bar.open_interest, Some(5.0));
+        assert_eq!(bar.close, Some(3.5));
+        assert!(bar.extra.is_empty());
+
+        let row = json!({ "Date": "2024-01-02", "Vol": 10, "Volume": 12 });
+        assert_eq!(OhlcBar::from_map(row.as_object().unwrap()).unwrap().volume, Some(12.0));
+    }
+
+    #[test]
+    fn without_derived_drops_model_columns_from_extra() {
+        let row = json!({ "Date": "2024-01-02", "Close": 1, "%change": 0.5, "M-no": 1, "id": 3, "Note": "x" });
+        let bar = OhlcBar::from_map(row.as_object().unwrap()).unwrap().without_derived();
+        assert_eq!(bar.extra.keys().collect::<Vec<_>>(), vec!["Note"]);
+    }
+}
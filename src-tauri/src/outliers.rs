@@ -0,0 +1,172 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OutlierMethod {
+    /// Robust z-score from the median absolute deviation of log returns.
+    #[default]
+    Mad,
+    /// Classic z-score from the mean and standard deviation of log returns.
+    ZScore,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct OutlierConfig {
+    #[serde(default)]
+    pub method: OutlierMethod,
+    /// Score above which a return is flagged; defaults to 3.5 for MAD and 3.0 for z-score.
+    #[serde(default)]
+    pub threshold: Option<f64>,
+    #[serde(default = "default_true")]
+    pub check_ohlc: bool,
+    /// Leave flagged rows out of the per-year normalization and monthly averages.
+    #[serde(default)]
+    pub exclude_from_normalization: bool,
+}
+
+/// Open, high, low, close of one bar.
+pub type Ohlc = (Option<f64>, Option<f64>, Option<f64>, Option<f64>);
+
+fn default_true() -> bool {
+    true
+}
+
+fn median(values: &mut [f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let mid = values.len() / 2;
+    Some(if values.len() % 2 == 1 { values[mid] } else { (values[mid - 1] + values[mid]) / 2.0 })
+}
+
+fn ohlc_issue(open: Option<f64>, high: Option<f64>, low: Option<f64>, close: Option<f64>) -> Option<&'static str> {
+    let prices = [open, high, low, close];
+    if prices.iter().flatten().any(|p| *p <= 0.0) {
+        return Some("non_positive_price");
+    }
+    let (Some(h), Some(l)) = (high, low) else { return None };
+    let body = [open, close];
+    if l > h || body.iter().flatten().any(|p| *p > h || *p < l) {
+        return Some("ohlc_inconsistent");
+    }
+    None
+}
+
+/// Flags date-sorted bars whose close-to-close return is extreme or whose OHLC values contradict
+/// each other. Returns one comma-separated reason list per bar (`None` when clean).
+pub fn flag_outliers(bars: &[Ohlc], config: &OutlierConfig) -> Vec<Option<String>> {
+    let mut reasons: Vec<Vec<&'static str>> = vec![Vec::new(); bars.len()];
+
+    if config.check_ohlc {
+        for (i, (o, h, l, c)) in bars.iter().enumerate() {
+            if let Some(issue) = ohlc_issue(*o, *h, *l, *c) {
+                reasons[i].push(issue);
+            }
+        }
+    }
+
+    let returns: Vec<Option<f64>> = (0..bars.len())
+        .map(|i| {
+            let prev = bars.get(i.checked_sub(1)?)?.3?;
+            let cur = bars[i].3?;
+            if prev > 0.0 && cur > 0.0 { Some((cur / prev).ln()) } else { None }
+        })
+        .collect();
+    let observed: Vec<f64> = returns.iter().flatten().copied().collect();
+
+    let scorer: Option<(f64, f64)> = match config.method {
+        OutlierMethod::Mad => median(&mut observed.clone()).and_then(|m| {
+            let mut deviations: Vec<f64> = observed.iter().map(|r| (r - m).abs()).collect();
+            // 0.6745 scales the MAD to a standard deviation under normality.
+            median(&mut deviations).filter(|mad| *mad > 0.0).map(|mad| (m, mad / 0.6745))
+        }),
+        OutlierMethod::ZScore => {
+            let n = observed.len() as f64;
+            if observed.len() < 2 {
+                None
+            } else {
+                let mean = observed.iter().sum::<f64>() / n;
+                let std = (observed.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt();
+                (std > 0.0).then_some((mean, std))
+            }
+        }
+    };
+
+    let threshold = config.threshold.unwrap_or(match config.method {
+        OutlierMethod::Mad => 3.5,
+        OutlierMethod::ZScore => 3.0,
+    });
+    if let Some((center, scale)) = scorer {
+        for (i, r) in returns.iter().enumerate() {
+            if r.is_some_and(|r| ((r - center) / scale).abs() > threshold) {
+                reasons[i].push("return_outlier");
+            }
+        }
+    }
+
+    reasons
+        .into_iter()
+        .map(|r| if r.is_empty() { None } else { Some(r.join(",")) })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn closes(values: &[f64]) -> Vec<Ohlc> {
+        values.iter().map(|c| (None, None, None, Some(*c))).collect()
+    }
+
+    fn config(method: OutlierMethod, threshold: Option<f64>) -> OutlierConfig {
+        OutlierConfig { method, threshold, check_ohlc: true, exclude_from_normalization: false }
+    }
+
+    fn flagged(flags: &[Option<String>]) -> Vec<usize> {
+        flags.iter().enumerate().filter(|(_, f)| f.is_some()).map(|(i, _)| i).collect()
+    }
+
+    /// Small alternating moves around a one-bar spike to 150.
+    const SPIKE: [f64; 9] = [100.0, 101.0, 100.0, 101.0, 100.0, 150.0, 100.0, 101.0, 100.0];
+
+    #[test]
+    fn mad_flags_the_jump_and_the_return_from_it() {
+        let flags = flag_outliers(&closes(&SPIKE), &config(OutlierMethod::Mad, None));
+        assert_eq!(flagged(&flags), vec![5, 6]);
+        assert_eq!(flags[5].as_deref(), Some("return_outlier"));
+    }
+
+    #[test]
+    fn z_score_is_diluted_by_the_outliers_it_looks_for() {
+        // The spike inflates the sample deviation to about 0.22, scoring the jump near 1.9.
+        assert!(flagged(&flag_outliers(&closes(&SPIKE), &config(OutlierMethod::ZScore, None))).is_empty());
+        assert_eq!(flagged(&flag_outliers(&closes(&SPIKE), &config(OutlierMethod::ZScore, Some(1.5)))), vec![5, 6]);
+    }
+
+    #[test]
+    fn flat_or_missing_closes_have_no_return_flags() {
+        assert!(flagged(&flag_outliers(&closes(&[5.0; 6]), &config(OutlierMethod::Mad, None))).is_empty());
+        let mut bars = closes(&SPIKE);
+        bars[5].3 = None;
+        // Without the spike's close, neither return around it can be measured.
+        assert!(flagged(&flag_outliers(&bars, &config(OutlierMethod::Mad, None))).is_empty());
+    }
+
+    #[test]
+    fn contradictory_ohlc_values_are_flagged_when_checked() {
+        let bars: Vec<Ohlc> = vec![
+            (Some(10.0), Some(11.0), Some(9.0), Some(10.5)),
+            (Some(10.0), Some(10.2), Some(9.0), Some(10.5)),
+            (Some(10.0), Some(9.0), Some(11.0), None),
+            (Some(-1.0), Some(11.0), Some(9.0), Some(10.0)),
+        ];
+        let flags = flag_outliers(&bars, &config(OutlierMethod::Mad, None));
+        assert_eq!(
+            flags,
+            vec![None, Some("ohlc_inconsistent".into()), Some("ohlc_inconsistent".into()), Some("non_positive_price".into())]
+        );
+        let unchecked = OutlierConfig { check_ohlc: false, ..config(OutlierMethod::Mad, None) };
+        assert_eq!(flag_outliers(&bars, &unchecked), vec![None; 4]);
+    }
+}
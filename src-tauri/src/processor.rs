@@ -1,14 +1,70 @@
-use chrono::{Datelike, Duration, NaiveDate};
+use crate::cycles::YearFilter;
+use crate::detrend::{decompose, DetrendMethod};
+use crate::gaps::FillPolicy;
+use crate::model::{OhlcBar, ProcessedBar};
+use crate::outliers::{flag_outliers, OutlierConfig};
+use chrono::{Datelike, Duration, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 use std::collections::HashMap;
 
-fn excel_serial_to_date(serial: f64) -> Option<NaiveDate> {
+/// A bar's timestamp as given in the source: local wall-clock time, plus the UTC offset when the
+/// source carried one. Daily bars have `intraday == false` and keep the plain `%Y-%m-%d` format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Timestamp {
+    pub local: NaiveDateTime,
+    pub offset: Option<FixedOffset>,
+    pub intraday: bool,
+}
+
+impl Timestamp {
+    fn daily(date: NaiveDate) -> Self {
+        Timestamp { local: date.and_time(NaiveTime::MIN), offset: None, intraday: false }
+    }
+
+    pub fn date(&self) -> NaiveDate {
+        self.local.date()
+    }
+
+    /// Instant used for ordering; bars with an offset are compared in UTC.
+    pub fn sort_key(&self) -> NaiveDateTime {
+        match self.offset {
+            Some(o) => self.local - Duration::seconds(o.local_minus_utc() as i64),
+            None => self.local,
+        }
+    }
+
+    /// Storage format for the `Date` column.
+    pub fn format(&self) -> String {
+        if !self.intraday {
+            return self.local.format("%Y-%m-%d").to_string();
+        }
+        match self.offset.and_then(|o| self.local.and_local_timezone(o).single()) {
+            Some(dt) => dt.format("%Y-%m-%dT%H:%M:%S%:z").to_string(),
+            None => self.local.format("%Y-%m-%dT%H:%M:%S").to_string(),
+        }
+    }
+}
+
+fn excel_serial_to_timestamp(serial: f64) -> Option<Timestamp> {
     // Match the Electron app's conversion: (serial - 25569) days since Unix epoch.
     let days = (serial - 25569.0).floor() as i64;
-    NaiveDate::from_ymd_opt(1970, 1, 1).map(|d| d + Duration::days(days))
+    let date = NaiveDate::from_ymd_opt(1970, 1, 1).map(|d| d + Duration::days(days))?;
+    // The fractional part is the time of day.
+    let seconds = ((serial - serial.floor()) * 86_400.0).round() as i64;
+    if seconds == 0 || seconds >= 86_400 {
+        return Some(Timestamp::daily(date));
+    }
+    Some(Timestamp {
+        local: date.and_time(NaiveTime::MIN) + Duration::seconds(seconds),
+        offset: None,
+        intraday: true,
+    })
 }
 
-fn parse_date(value: &Value) -> Option<NaiveDate> {
+const NAIVE_DATETIME_FORMATS: [&str; 4] = ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"];
+
+pub(crate) fn parse_timestamp(value: &Value) -> Option<Timestamp> {
     match value {
         Value::String(s) => {
             let trimmed = s.trim();
@@ -17,24 +73,37 @@ fn parse_date(value: &Value) -> Option<NaiveDate> {
             }
             // Try yyyy-mm-dd (what the app typically uses)
             if let Ok(d) = NaiveDate::parse_from_str(trimmed, "%Y-%m-%d") {
-                return Some(d);
+                return Some(Timestamp::daily(d));
+            }
+            // Try RFC3339-ish timestamps, keeping the offset
+            let with_offset = chrono::DateTime::parse_from_rfc3339(trimmed)
+                .or_else(|_| chrono::DateTime::parse_from_str(trimmed, "%Y-%m-%d %H:%M:%S%.f%:z"));
+            if let Ok(dt) = with_offset {
+                return Some(Timestamp { local: dt.naive_local(), offset: Some(*dt.offset()), intraday: true });
             }
-            // Try RFC3339-ish timestamps
-            if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(trimmed) {
-                return Some(dt.date_naive());
+            // Try timestamps without an offset
+            if let Some(dt) = NAIVE_DATETIME_FORMATS
+                .iter()
+                .find_map(|f| NaiveDateTime::parse_from_str(trimmed, f).ok())
+            {
+                return Some(Timestamp { local: dt, offset: None, intraday: true });
             }
             // Try numeric string (Excel serial)
             if let Ok(n) = trimmed.parse::<f64>() {
-                return excel_serial_to_date(n);
+                return excel_serial_to_timestamp(n);
             }
             None
         }
-        Value::Number(n) => n.as_f64().and_then(excel_serial_to_date),
+        Value::Number(n) => n.as_f64().and_then(excel_serial_to_timestamp),
         _ => None,
     }
 }
 
-fn as_f64(value: &Value) -> Option<f64> {
+pub(crate) fn parse_date(value: &Value) -> Option<NaiveDate> {
+    parse_timestamp(value).map(|t| t.date())
+}
+
+pub(crate) fn as_f64(value: &Value) -> Option<f64> {
     match value {
         Value::Number(n) => n.as_f64(),
         Value::String(s) => s.trim().parse::<f64>().ok(),
@@ -42,30 +111,250 @@ fn as_f64(value: &Value) -> Option<f64> {
     }
 }
 
-pub fn calculate_derived_columns(input: &[Map<String, Value>], replace_nan_with_zero: bool) -> Vec<Map<String, Value>> {
-    // Normalize and sort by date
+/// How values are rescaled within each year for the `normalized` and `True_Seasonal` stages.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NormalizationMethod {
+    /// (x - min) / (max - min) * 100.
+    #[default]
+    MinMax,
+    /// Share of the year's values at or below x, 0-100 (ties share the average rank).
+    PercentileRank,
+    /// (x - mean) / std; unbounded, centred on 0.
+    ZScore,
+    /// Min-max after clipping to the given percentiles (0-100), so single extreme days do not dominate.
+    WinsorizedMinMax {
+        #[serde(default = "default_winsor_lower")]
+        lower: f64,
+        #[serde(default = "default_winsor_upper")]
+        upper: f64,
+    },
+}
+
+fn default_winsor_lower() -> f64 {
+    5.0
+}
+
+fn default_winsor_upper() -> f64 {
+    95.0
+}
+
+impl NormalizationMethod {
+    pub fn describe(&self) -> String {
+        match self {
+            NormalizationMethod::MinMax => "Min-max".to_string(),
+            NormalizationMethod::PercentileRank => "Percentile rank".to_string(),
+            NormalizationMethod::ZScore => "Z-score".to_string(),
+            NormalizationMethod::WinsorizedMinMax { lower, upper } => format!("Winsorized min-max ({}-{} pct)", lower, upper),
+        }
+    }
+}
+
+/// How missing prices enter the calculations and what derived values that cannot be computed become.
+/// Source prices are always stored as given, missing ones as NULL; aggregates (normalization ranges,
+/// month averages) always skip missing values.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum NanPolicy {
+    /// Keep derived values missing (stored as NULL).
+    Null,
+    /// Write 0.0 in place of derived values.
+    Zero,
+    /// Compute from the previous bar's price where one is missing.
+    ForwardFill,
+    /// Leave bars without a close out of the calculations; they are stored without derived values.
+    Exclude,
+}
+
+impl NanPolicy {
+    fn output(self, v: f64) -> Option<f64> {
+        if v.is_finite() {
+            Some(v)
+        } else if self == NanPolicy::Zero {
+            Some(0.0)
+        } else {
+            None
+        }
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            NanPolicy::Null => "Leave missing",
+            NanPolicy::Zero => "Replace with 0",
+            NanPolicy::ForwardFill => "Forward-fill",
+            NanPolicy::Exclude => "Exclude bar",
+        }
+    }
+}
+
+/// Per-asset processing choices, persisted so edits recompute the same way.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ProcessingOptions {
+    #[serde(default)]
+    pub outliers: Option<OutlierConfig>,
+    /// Falls back to the caller's `replace_nan` flag when unset.
+    #[serde(default)]
+    pub nan_policy: Option<NanPolicy>,
+    #[serde(default)]
+    pub normalization: NormalizationMethod,
+    #[serde(default)]
+    pub seasonal_normalization: NormalizationMethod,
+    /// Remove the trend from closes before the normalized/seasonal stages.
+    #[serde(default)]
+    pub detrend: Option<DetrendMethod>,
+    /// How missing sessions are filled before anything is derived.
+    #[serde(default)]
+    pub fill_policy: FillPolicy,
+}
+
+impl ProcessingOptions {
+    /// These options with the fields present in `changes` replaced; fields not sent keep their value.
+    pub fn merged(&self, changes: Map<String, Value>) -> serde_json::Result<Self> {
+        let mut current = match serde_json::to_value(self)? {
+            Value::Object(m) => m,
+            _ => Map::new(),
+        };
+        current.extend(changes);
+        serde_json::from_value(Value::Object(current))
+    }
+
+    /// Human-readable setting/value pairs for exports.
+    pub fn summary(&self) -> Vec<(&'static str, String)> {
+        let outliers = match &self.outliers {
+            Some(o) if o.exclude_from_normalization => "Flagged, excluded from normalization",
+            Some(_) => "Flagged",
+            None => "Off",
+        };
+        vec![
+            ("Missing values", self.nan_policy.unwrap_or(NanPolicy::Null).describe().to_string()),
+            ("normalized", self.normalization.describe()),
+            ("True_Seasonal", self.seasonal_normalization.describe()),
+            ("Outliers", outliers.to_string()),
+            ("Detrending", self.detrend.map_or_else(|| "Off".to_string(), |d| d.describe())),
+            ("Gap filling", self.fill_policy.describe().to_string()),
+        ]
+    }
+}
+
+/// Linear-interpolated percentile (0-100) of sorted values.
+fn percentile_sorted(sorted: &[f64], pct: f64) -> f64 {
+    let pos = (pct.clamp(0.0, 100.0) / 100.0) * (sorted.len() - 1) as f64;
+    let lo = pos.floor() as usize;
+    let hi = pos.ceil() as usize;
+    sorted[lo] + (sorted[hi] - sorted[lo]) * (pos - lo as f64)
+}
+
+/// Rescales one year's values; non-finite inputs and degenerate years yield NaN.
+fn normalize_values(values: &[f64], method: NormalizationMethod) -> Vec<f64> {
+    let mut sorted: Vec<f64> = values.iter().copied().filter(|v| v.is_finite()).collect();
+    if sorted.is_empty() {
+        return vec![f64::NAN; values.len()];
+    }
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+    let min_max = |lo: f64, hi: f64| -> Vec<f64> {
+        let range = hi - lo;
+        values
+            .iter()
+            .map(|v| if range == 0.0 || !v.is_finite() { f64::NAN } else { ((v.clamp(lo, hi) - lo) / range) * 100.0 })
+            .collect()
+    };
+
+    match method {
+        NormalizationMethod::MinMax => min_max(sorted[0], sorted[sorted.len() - 1]),
+        NormalizationMethod::WinsorizedMinMax { lower, upper } => {
+            min_max(percentile_sorted(&sorted, lower.min(upper)), percentile_sorted(&sorted, upper.max(lower)))
+        }
+        NormalizationMethod::PercentileRank => {
+            let n = sorted.len();
+            values
+                .iter()
+                .map(|v| {
+                    if !v.is_finite() || n < 2 {
+                        return f64::NAN;
+                    }
+                    let below = sorted.partition_point(|x| x < v);
+                    let through = sorted.partition_point(|x| x <= v);
+                    let rank = (below + through - 1) as f64 / 2.0;
+                    rank / (n - 1) as f64 * 100.0
+                })
+                .collect()
+        }
+        NormalizationMethod::ZScore => {
+            let n = sorted.len() as f64;
+            let mean = sorted.iter().sum::<f64>() / n;
+            let std = (sorted.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n).sqrt();
+            values
+                .iter()
+                .map(|v| if std == 0.0 || !v.is_finite() { f64::NAN } else { (v - mean) / std })
+                .collect()
+        }
+    }
+}
+
+fn normalize_by_year(years: &[i32], values: &[f64], method: NormalizationMethod) -> Vec<f64> {
+    let mut groups: HashMap<i32, Vec<usize>> = HashMap::new();
+    for (i, y) in years.iter().enumerate() {
+        groups.entry(*y).or_default().push(i);
+    }
+    let mut out = vec![f64::NAN; values.len()];
+    for idxs in groups.values() {
+        let group: Vec<f64> = idxs.iter().map(|i| values[*i]).collect();
+        for (i, v) in idxs.iter().zip(normalize_values(&group, method)) {
+            out[*i] = v;
+        }
+    }
+    out
+}
+
+pub fn calculate_derived_columns(input: &[OhlcBar], replace_nan_with_zero: bool, options: &ProcessingOptions) -> Vec<ProcessedBar> {
+    let policy = options.nan_policy.unwrap_or(if replace_nan_with_zero { NanPolicy::Zero } else { NanPolicy::Null });
+
+    // Normalize and sort by timestamp (intraday bars keep their time of day)
     let mut rows: Vec<_> = input
         .iter()
-        .filter_map(|row| {
-            let date = parse_date(row.get("Date")?)?;
-            let open = row.get("Open").and_then(as_f64);
-            let high = row.get("High").and_then(as_f64);
-            let low = row.get("Low").and_then(as_f64);
-            let close = row.get("Close").and_then(as_f64);
-            Some((date, open, high, low, close))
+        .filter_map(|bar| {
+            // Previously derived columns are dropped here and recomputed below
+            let source = bar.clone().without_derived();
+            let timestamp = source.timestamp()?;
+            Some((timestamp, source))
         })
         .collect();
 
-    rows.sort_by_key(|r| r.0);
+    rows.sort_by_key(|r| r.0.sort_key());
+
+    // Bars left out of the calculations are still stored, unchanged and without derived values.
+    let (rows, skipped): (Vec<_>, Vec<_>) = rows.into_iter().partition(|r| policy != NanPolicy::Exclude || r.1.close.is_some());
+
+    // Prices the calculations see; the source bars themselves are stored as given.
+    let mut prices: Vec<[Option<f64>; 4]> = rows.iter().map(|(_, b)| [b.open, b.high, b.low, b.close]).collect();
+    if policy == NanPolicy::ForwardFill {
+        let mut last: [Option<f64>; 4] = [None; 4];
+        for bar in &mut prices {
+            for (v, last) in bar.iter_mut().zip(&mut last) {
+                match *v {
+                    Some(x) => *last = Some(x),
+                    None => *v = *last,
+                }
+            }
+        }
+    }
+
+    let outlier_flags: Vec<Option<String>> = match &options.outliers {
+        Some(config) => flag_outliers(&prices.iter().map(|p| (p[0], p[1], p[2], p[3])).collect::<Vec<_>>(), config),
+        None => vec![None; rows.len()],
+    };
+    let exclude_outliers = options.outliers.as_ref().is_some_and(|c| c.exclude_from_normalization);
 
     #[derive(Clone)]
     struct Row {
-        date: NaiveDate,
-        open: f64,
-        high: f64,
-        low: f64,
+        timestamp: Timestamp,
         close: f64,
+        source: OhlcBar,
+        outlier: Option<String>,
         pct_change: f64,
+        components: Option<(f64, f64, f64)>,
+        trend_change: f64,
         month_no: i64,
         normalized: f64,
         average_norm: f64,
@@ -75,31 +364,21 @@ pub fn calculate_derived_columns(input: &[Map<String, Value>], replace_nan_with_
 
     let mut processed: Vec<Row> = Vec::with_capacity(rows.len());
 
-    for (idx, (date, open, high, low, close)) in rows.into_iter().enumerate() {
-        let open = open.unwrap_or(0.0);
-        let high = high.unwrap_or(0.0);
-        let low = low.unwrap_or(0.0);
-        let close = close.unwrap_or(0.0);
+    for (idx, (((timestamp, source), price), outlier)) in rows.into_iter().zip(prices).zip(outlier_flags).enumerate() {
+        let date = timestamp.date();
+        let close = price[3].unwrap_or(f64::NAN);
 
-        let pct_change = if idx == 0 {
-            if replace_nan_with_zero { 0.0 } else { f64::NAN }
-        } else {
-            let prev_close = processed[idx - 1].close;
-            let change = close - prev_close;
-            if replace_nan_with_zero {
-                if change.is_finite() { change } else { 0.0 }
-            } else {
-                change
-            }
-        };
+        // Missing values stay NaN through the calculations; the policy is applied on output.
+        let pct_change = if idx == 0 { f64::NAN } else { close - processed[idx - 1].close };
 
         processed.push(Row {
-            date,
-            open,
-            high,
-            low,
+            timestamp,
             close,
+            source,
+            outlier,
             pct_change,
+            components: None,
+            trend_change: f64::NAN,
             month_no: date.month() as i64,
             normalized: 0.0,
             average_norm: 0.0,
@@ -108,25 +387,27 @@ pub fn calculate_derived_columns(input: &[Map<String, Value>], replace_nan_with_
         });
     }
 
-    // normalized per year
-    let mut year_min_max: HashMap<i32, (f64, f64)> = HashMap::new();
-    for r in &processed {
-        let entry = year_min_max.entry(r.year).or_insert((f64::INFINITY, f64::NEG_INFINITY));
-        if r.pct_change.is_finite() {
-            entry.0 = entry.0.min(r.pct_change);
-            entry.1 = entry.1.max(r.pct_change);
+    // Optional trend removal: the seasonal stages then work on changes of close minus trend
+    if let Some(method) = options.detrend {
+        let dates: Vec<NaiveDate> = processed.iter().map(|r| r.timestamp.date()).collect();
+        let closes: Vec<f64> = processed.iter().map(|r| r.close).collect();
+        let parts = decompose(&dates, &closes, method);
+        for (i, r) in processed.iter_mut().enumerate() {
+            r.components = Some((parts.trend[i], parts.seasonal[i], parts.residual[i]));
+            r.trend_change = parts.trend_change[i];
         }
     }
+    let detrended_change = |i: usize| -> f64 {
+        let r = &processed[i];
+        if r.components.is_some() { r.pct_change - r.trend_change } else { r.pct_change }
+    };
 
-    for r in &mut processed {
-        let (min, max) = year_min_max.get(&r.year).copied().unwrap_or((0.0, 0.0));
-        let range = max - min;
-        let val = if range == 0.0 || !r.pct_change.is_finite() {
-            if replace_nan_with_zero { 0.0 } else { f64::NAN }
-        } else {
-            ((r.pct_change - min) / range) * 100.0
-        };
-        r.normalized = if replace_nan_with_zero { if val.is_finite() { val } else { 0.0 } } else { val };
+    // normalized per year
+    let excluded = |r: &Row| exclude_outliers && r.outlier.is_some();
+    let years: Vec<i32> = processed.iter().map(|r| r.year).collect();
+    let changes: Vec<f64> = (0..processed.len()).map(|i| if excluded(&processed[i]) { f64::NAN } else { detrended_change(i) }).collect();
+    for (r, val) in processed.iter_mut().zip(normalize_by_year(&years, &changes, options.normalization)) {
+        r.normalized = val;
     }
 
     // Average_Norm cumulative by month
@@ -134,55 +415,1047 @@ pub fn calculate_derived_columns(input: &[Map<String, Value>], replace_nan_with_
     let mut month_count: [u32; 12] = [0; 12];
     for r in &mut processed {
         let idx = (r.month_no.clamp(1, 12) - 1) as usize;
-        if r.normalized.is_finite() {
+        if r.normalized.is_finite() && !excluded(r) {
             month_sum[idx] += r.normalized;
             month_count[idx] += 1;
         }
-        let avg = if month_count[idx] > 0 {
+        r.average_norm = if month_count[idx] > 0 {
             month_sum[idx] / month_count[idx] as f64
         } else {
-            0.0
+            f64::NAN
         };
-        r.average_norm = if replace_nan_with_zero { if avg.is_finite() { avg } else { 0.0 } } else { avg };
     }
 
     // True_Seasonal per year based on Average_Norm
-    let mut year_avg_min_max: HashMap<i32, (f64, f64)> = HashMap::new();
-    for r in &processed {
-        let entry = year_avg_min_max.entry(r.year).or_insert((f64::INFINITY, f64::NEG_INFINITY));
-        if r.average_norm.is_finite() {
-            entry.0 = entry.0.min(r.average_norm);
-            entry.1 = entry.1.max(r.average_norm);
-        }
+    let averages: Vec<f64> = processed.iter().map(|r| r.average_norm).collect();
+    for (r, val) in processed.iter_mut().zip(normalize_by_year(&years, &averages, options.seasonal_normalization)) {
+        r.true_seasonal = val;
     }
 
-    for r in &mut processed {
-        let (min, max) = year_avg_min_max.get(&r.year).copied().unwrap_or((0.0, 0.0));
-        let range = max - min;
-        let val = if range == 0.0 || !r.average_norm.is_finite() {
-            if replace_nan_with_zero { 0.0 } else { f64::NAN }
-        } else {
-            ((r.average_norm - min) / range) * 100.0
+    let skipped = skipped.into_iter().map(|(timestamp, source)| {
+        let bar = ProcessedBar {
+            bar: OhlcBar { date: Some(timestamp.format()), ..source },
+            month_no: Some(timestamp.date().month() as i64),
+            ..Default::default()
         };
-        r.true_seasonal = if replace_nan_with_zero { if val.is_finite() { val } else { 0.0 } } else { val };
+        (timestamp.sort_key(), bar)
+    });
+    let mut out: Vec<(NaiveDateTime, ProcessedBar)> = processed
+        .into_iter()
+        .map(|r| {
+            let bar = ProcessedBar {
+                bar: OhlcBar { date: Some(r.timestamp.format()), ..r.source },
+                pct_change: policy.output(r.pct_change),
+                month_no: Some(r.month_no),
+                normalized: policy.output(r.normalized),
+                average_norm: policy.output(r.average_norm),
+                true_seasonal: policy.output(r.true_seasonal),
+                outlier: r.outlier,
+                trend: r.components.and_then(|c| policy.output(c.0)),
+                seasonal: r.components.and_then(|c| policy.output(c.1)),
+                residual: r.components.and_then(|c| policy.output(c.2)),
+                ..Default::default()
+            };
+            (r.timestamp.sort_key(), bar)
+        })
+        .chain(skipped)
+        .collect();
+    out.sort_by_key(|r| r.0);
+    out.into_iter().map(|r| r.1).collect()
+}
+
+#[derive(Serialize, Clone)]
+pub struct YearPathPoint {
+    pub date: String,
+    pub day_of_year: u32,
+    pub value: f64,
+}
+
+#[derive(Serialize)]
+pub struct AnalogYear {
+    pub year: i32,
+    pub correlation: f64,
+    pub ytd_change: f64,
+    pub full_year_change: f64,
+    pub path: Vec<YearPathPoint>,
+}
+
+#[derive(Serialize)]
+pub struct AnalogYearsResult {
+    pub current_year: i32,
+    pub current_path: Vec<YearPathPoint>,
+    pub analogs: Vec<AnalogYear>,
+}
+
+/// Date-sorted closes, one per date; with several bars on a date (intraday data) the last one is the day's close.
+pub(crate) fn close_series(input: &[ProcessedBar]) -> Vec<(NaiveDate, f64)> {
+    let series: std::collections::BTreeMap<NaiveDate, f64> = input
+        .iter()
+        .filter_map(|row| Some((row.bar.parsed_date()?, row.bar.close?)))
+        .collect();
+    series.into_iter().collect()
+}
+
+pub(crate) fn pearson(xs: &[f64], ys: &[f64]) -> Option<f64> {
+    let n = xs.len().min(ys.len());
+    if n < 2 {
+        return None;
+    }
+    let mean_x = xs[..n].iter().sum::<f64>() / n as f64;
+    let mean_y = ys[..n].iter().sum::<f64>() / n as f64;
+    let (mut cov, mut var_x, mut var_y) = (0.0, 0.0, 0.0);
+    for i in 0..n {
+        let dx = xs[i] - mean_x;
+        let dy = ys[i] - mean_y;
+        cov += dx * dy;
+        var_x += dx * dx;
+        var_y += dy * dy;
+    }
+    if var_x == 0.0 || var_y == 0.0 {
+        return None;
+    }
+    Some(cov / (var_x.sqrt() * var_y.sqrt()))
+}
+
+/// Year-to-date paths per calendar year, expressed as % change from the year's first close.
+pub(crate) fn year_paths(series: &[(NaiveDate, f64)]) -> Vec<(i32, Vec<YearPathPoint>)> {
+    let mut out: Vec<(i32, Vec<YearPathPoint>)> = Vec::new();
+    let mut base = 0.0;
+    for (date, close) in series {
+        let year = date.year();
+        if out.last().map(|(y, _)| *y) != Some(year) {
+            out.push((year, Vec::new()));
+            base = *close;
+        }
+        if base == 0.0 {
+            continue;
+        }
+        if let Some((_, path)) = out.last_mut() {
+            path.push(YearPathPoint {
+                date: date.format("%Y-%m-%d").to_string(),
+                day_of_year: date.ordinal(),
+                value: (close / base - 1.0) * 100.0,
+            });
+        }
     }
+    out.retain(|(_, path)| !path.is_empty());
+    out
+}
+
+/// Value of `path` on `day_of_year`, carrying the last observation forward across non-trading days.
+pub(crate) fn path_value_at(path: &[YearPathPoint], day_of_year: u32) -> Option<f64> {
+    let idx = path.partition_point(|p| p.day_of_year <= day_of_year);
+    if idx == 0 { None } else { Some(path[idx - 1].value) }
+}
+
+/// Average year-to-date change (percent) by day of year across the given year paths.
+pub(crate) fn seasonal_composite(paths: &[(i32, Vec<YearPathPoint>)]) -> Vec<(u32, f64)> {
+    (1..=366)
+        .filter_map(|day| {
+            let values: Vec<f64> = paths.iter().filter_map(|(_, path)| path_value_at(path, day)).collect();
+            mean(&values).map(|m| (day, m))
+        })
+        .collect()
+}
+
+/// Ranks past years by how closely their year-to-date path correlates with the most recent year's.
+pub fn find_analog_years(input: &[ProcessedBar], top_n: usize) -> AnalogYearsResult {
+    let series = close_series(input);
+    let mut paths = year_paths(&series);
+
+    let Some((current_year, current_path)) = paths.pop() else {
+        return AnalogYearsResult { current_year: 0, current_path: vec![], analogs: vec![] };
+    };
+    let last_day = current_path.last().map(|p| p.day_of_year).unwrap_or(0);
 
-    // Convert back to JSON rows
-    processed
+    let mut analogs: Vec<AnalogYear> = paths
         .into_iter()
-        .map(|r| {
-            let mut m = Map::new();
-            m.insert("Date".to_string(), Value::String(r.date.format("%Y-%m-%d").to_string()));
-            m.insert("Open".to_string(), Value::Number(serde_json::Number::from_f64(r.open).unwrap_or_else(|| serde_json::Number::from(0))));
-            m.insert("High".to_string(), Value::Number(serde_json::Number::from_f64(r.high).unwrap_or_else(|| serde_json::Number::from(0))));
-            m.insert("Low".to_string(), Value::Number(serde_json::Number::from_f64(r.low).unwrap_or_else(|| serde_json::Number::from(0))));
-            m.insert("Close".to_string(), Value::Number(serde_json::Number::from_f64(r.close).unwrap_or_else(|| serde_json::Number::from(0))));
-            m.insert("%change".to_string(), Value::Number(serde_json::Number::from_f64(r.pct_change).unwrap_or_else(|| serde_json::Number::from(0))));
-            m.insert("M-no".to_string(), Value::Number(serde_json::Number::from(r.month_no)));
-            m.insert("normalized".to_string(), Value::Number(serde_json::Number::from_f64(r.normalized).unwrap_or_else(|| serde_json::Number::from(0))));
-            m.insert("Average_Norm".to_string(), Value::Number(serde_json::Number::from_f64(r.average_norm).unwrap_or_else(|| serde_json::Number::from(0))));
-            m.insert("True_Seasonal".to_string(), Value::Number(serde_json::Number::from_f64(r.true_seasonal).unwrap_or_else(|| serde_json::Number::from(0))));
-            m
+        .filter_map(|(year, path)| {
+            let (xs, ys): (Vec<f64>, Vec<f64>) = current_path
+                .iter()
+                .filter_map(|p| path_value_at(&path, p.day_of_year).map(|v| (p.value, v)))
+                .unzip();
+            let correlation = pearson(&xs, &ys)?;
+            Some(AnalogYear {
+                year,
+                correlation,
+                ytd_change: path_value_at(&path, last_day).unwrap_or(0.0),
+                full_year_change: path.last().map(|p| p.value).unwrap_or(0.0),
+                path,
+            })
+        })
+        .collect();
+
+    analogs.sort_by(|a, b| b.correlation.partial_cmp(&a.correlation).unwrap_or(std::cmp::Ordering::Equal));
+    analogs.truncate(top_n);
+
+    AnalogYearsResult { current_year, current_path, analogs }
+}
+
+#[derive(Serialize)]
+pub struct ForecastPoint {
+    pub date: String,
+    pub day_of_year: u32,
+    /// Latest close carried forward by the average seasonal move from the as-of day.
+    pub projected: f64,
+    /// Projected price at each requested percentile of the past years' moves, in `percentiles` order.
+    pub bands: Vec<f64>,
+}
+
+#[derive(Serialize)]
+pub struct ForecastLookback {
+    /// Number of past years requested; 0 means all available years.
+    pub lookback_years: usize,
+    pub years_used: Vec<i32>,
+    pub path: Vec<ForecastPoint>,
+}
+
+#[derive(Serialize)]
+pub struct SeasonalForecast {
+    pub as_of: String,
+    pub last_close: f64,
+    pub percentiles: Vec<f64>,
+    pub lookbacks: Vec<ForecastLookback>,
+}
+
+/// Projects the rest of the latest year from its last close using the day-of-year moves of past years.
+/// Only weekdays are projected.
+pub fn seasonal_forecast(input: &[ProcessedBar], lookbacks: &[usize], percentiles: &[f64]) -> Option<SeasonalForecast> {
+    let series = close_series(input);
+    let (as_of, last_close) = *series.last()?;
+    let mut paths = year_paths(&series);
+    paths.pop();
+    let from_day = as_of.ordinal();
+
+    // Price ratio of each past year from the as-of day onwards: (day of year, ratio).
+    let moves: Vec<(i32, Vec<(u32, f64)>)> = paths
+        .iter()
+        .filter_map(|(year, path)| {
+            let base = 1.0 + path_value_at(path, from_day)? / 100.0;
+            if base <= 0.0 {
+                return None;
+            }
+            let ratios = (from_day + 1..=366)
+                .filter_map(|day| path_value_at(path, day).map(|v| (day, (1.0 + v / 100.0) / base)))
+                .collect();
+            Some((*year, ratios))
+        })
+        .collect();
+
+    let future_days: Vec<(NaiveDate, u32)> = (from_day + 1..=366)
+        .filter_map(|day| NaiveDate::from_yo_opt(as_of.year(), day).map(|d| (d, day)))
+        .filter(|(d, _)| !matches!(d.weekday(), chrono::Weekday::Sat | chrono::Weekday::Sun))
+        .collect();
+
+    let lookbacks = lookbacks
+        .iter()
+        .map(|&lookback| {
+            let take = if lookback == 0 { moves.len() } else { lookback.min(moves.len()) };
+            let used = &moves[moves.len() - take..];
+            let path = future_days
+                .iter()
+                .filter_map(|(date, day)| {
+                    let mut ratios: Vec<f64> = used
+                        .iter()
+                        .filter_map(|(_, r)| r.iter().find(|(d, _)| d == day).map(|(_, v)| *v))
+                        .collect();
+                    let projected = mean(&ratios)? * last_close;
+                    ratios.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+                    Some(ForecastPoint {
+                        date: date.format("%Y-%m-%d").to_string(),
+                        day_of_year: *day,
+                        projected,
+                        bands: percentiles.iter().map(|p| percentile_sorted(&ratios, *p) * last_close).collect(),
+                    })
+                })
+                .collect();
+            ForecastLookback { lookback_years: lookback, years_used: used.iter().map(|(y, _)| *y).collect(), path }
+        })
+        .collect();
+
+    Some(SeasonalForecast {
+        as_of: as_of.format("%Y-%m-%d").to_string(),
+        last_close,
+        percentiles: percentiles.to_vec(),
+        lookbacks,
+    })
+}
+
+/// Which per-year series the seasonal curve averages by day of year.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CurveBasis {
+    /// Year-to-date change in percent.
+    #[default]
+    YtdChange,
+    /// The stored `normalized` column.
+    Normalized,
+    /// Year-to-date change in percent, aligned on the stored trading day of the year ("TD-no") rather than the
+    /// calendar day, so `day_of_year` in the result counts sessions. Assets without a calendar give no curves.
+    TradingDay,
+}
+
+#[derive(Serialize)]
+pub struct SeasonalBandPoint {
+    pub day_of_year: u32,
+    pub mean: f64,
+    pub lower: f64,
+    pub upper: f64,
+}
+
+#[derive(Serialize)]
+pub struct SeasonalBands {
+    pub years: Vec<i32>,
+    pub resamples: usize,
+    pub confidence: f64,
+    pub seed: u64,
+    pub points: Vec<SeasonalBandPoint>,
+}
+
+/// SplitMix64: tiny and deterministic, which is all resampling needs.
+pub(crate) struct SplitMix64(pub u64);
+
+impl SplitMix64 {
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform index in `0..n`.
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+/// One row per year, one slot per day of year (1-366).
+fn year_curves(input: &[ProcessedBar], basis: CurveBasis) -> Vec<(i32, Vec<Option<f64>>)> {
+    match basis {
+        CurveBasis::YtdChange => year_paths(&close_series(input))
+            .into_iter()
+            .map(|(year, path)| {
+                let last = path.last().map(|p| p.day_of_year).unwrap_or(0);
+                (year, (1..=366).map(|day| if day <= last { path_value_at(&path, day) } else { None }).collect())
+            })
+            .collect(),
+        CurveBasis::Normalized => {
+            let mut years: std::collections::BTreeMap<i32, Vec<Option<f64>>> = std::collections::BTreeMap::new();
+            for row in input {
+                let (Some(date), Some(v)) = (row.bar.parsed_date(), row.normalized) else { continue };
+                years.entry(date.year()).or_insert_with(|| vec![None; 366])[date.ordinal0() as usize] = Some(v);
+            }
+            years.into_iter().collect()
+        }
+        CurveBasis::TradingDay => {
+            // Last bar of each date, as in `close_series`.
+            let sessions: std::collections::BTreeMap<NaiveDate, (i64, f64)> = input
+                .iter()
+                .filter_map(|row| Some((row.bar.parsed_date()?, (row.trading_day_no?, row.bar.close?))))
+                .collect();
+            let mut years: std::collections::BTreeMap<i32, (f64, Vec<Option<f64>>)> = std::collections::BTreeMap::new();
+            for (date, (day, close)) in sessions {
+                let (base, curve) = years.entry(date.year()).or_insert_with(|| (close, vec![None; 366]));
+                if *base != 0.0 && (1..=366).contains(&day) {
+                    curve[day as usize - 1] = Some((close / *base - 1.0) * 100.0);
+                }
+            }
+            years.into_iter().map(|(year, (_, curve))| (year, curve)).collect()
+        }
+    }
+}
+
+/// Mean seasonal curve with bootstrap bands: years are resampled with replacement `resamples` times and the
+/// band is the central `confidence` share of the resampled means. The same seed always gives the same bands.
+pub fn seasonal_bootstrap(input: &[ProcessedBar], basis: CurveBasis, resamples: usize, confidence: f64, seed: u64) -> SeasonalBands {
+    let curves = year_curves(input, basis);
+    let confidence = confidence.clamp(0.0, 1.0);
+    let years: Vec<i32> = curves.iter().map(|(y, _)| *y).collect();
+    let day_mean = |picks: &mut dyn Iterator<Item = usize>, day: usize| -> Option<f64> {
+        let values: Vec<f64> = picks.filter_map(|i| curves[i].1[day]).collect();
+        mean(&values)
+    };
+
+    let mut rng = SplitMix64(seed);
+    let mut resampled: Vec<Vec<f64>> = vec![Vec::new(); 366];
+    if !curves.is_empty() {
+        for _ in 0..resamples {
+            let picks: Vec<usize> = (0..curves.len()).map(|_| rng.below(curves.len())).collect();
+            for (day, out) in resampled.iter_mut().enumerate() {
+                if let Some(m) = day_mean(&mut picks.iter().copied(), day) {
+                    out.push(m);
+                }
+            }
+        }
+    }
+
+    let points = resampled
+        .into_iter()
+        .enumerate()
+        .filter_map(|(day, mut means)| {
+            let mean = day_mean(&mut (0..curves.len()), day)?;
+            means.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+            let (lower, upper) = if means.is_empty() {
+                (mean, mean)
+            } else {
+                (percentile_sorted(&means, (1.0 - confidence) / 2.0 * 100.0), percentile_sorted(&means, (1.0 + confidence) / 2.0 * 100.0))
+            };
+            Some(SeasonalBandPoint { day_of_year: day as u32 + 1, mean, lower, upper })
+        })
+        .collect();
+
+    SeasonalBands { years, resamples, confidence, seed, points }
+}
+
+#[derive(Serialize)]
+pub struct CurvePoint {
+    pub day_of_year: u32,
+    pub value: f64,
+}
+
+#[derive(Serialize)]
+pub struct FilteredSeasonality {
+    pub included_years: Vec<i32>,
+    /// Average `normalized` value per calendar month over the included years (January first).
+    pub monthly_average_norm: Vec<Option<f64>>,
+    /// Day-of-year composite of the chosen basis over the included years.
+    pub curve: Vec<CurvePoint>,
+}
+
+/// Last-close change from one calendar year to the next, in percent.
+fn annual_returns(series: &[(NaiveDate, f64)]) -> std::collections::BTreeMap<i32, f64> {
+    let mut last_close: std::collections::BTreeMap<i32, f64> = std::collections::BTreeMap::new();
+    for (date, close) in series {
+        last_close.insert(date.year(), *close);
+    }
+    last_close
+        .iter()
+        .filter_map(|(year, close)| {
+            let prev = last_close.get(&(year - 1)).filter(|p| **p != 0.0)?;
+            Some((*year, (close / prev - 1.0) * 100.0))
         })
         .collect()
 }
+
+/// Seasonal composites built only from the years matching `filter`.
+pub fn filtered_seasonality(input: &[ProcessedBar], filter: &YearFilter, basis: CurveBasis) -> FilteredSeasonality {
+    let returns = annual_returns(&close_series(input));
+    let curves: Vec<(i32, Vec<Option<f64>>)> = year_curves(input, basis)
+        .into_iter()
+        .filter(|(year, _)| filter.matches(*year, &returns))
+        .collect();
+    let included_years: Vec<i32> = curves.iter().map(|(y, _)| *y).collect();
+
+    let mut by_month: Vec<Vec<f64>> = vec![Vec::new(); 12];
+    for row in input {
+        let (Some(date), Some(v)) = (row.bar.parsed_date(), row.normalized) else { continue };
+        if included_years.contains(&date.year()) {
+            by_month[date.month0() as usize].push(v);
+        }
+    }
+
+    let curve = (0..366)
+        .filter_map(|day| {
+            let values: Vec<f64> = curves.iter().filter_map(|(_, c)| c[day]).collect();
+            mean(&values).map(|value| CurvePoint { day_of_year: day as u32 + 1, value })
+        })
+        .collect();
+
+    FilteredSeasonality {
+        included_years,
+        monthly_average_norm: by_month.iter().map(|v| mean(v)).collect(),
+        curve,
+    }
+}
+
+#[derive(Serialize)]
+pub struct MonthVolumeStats {
+    pub month: u32,
+    pub observations: usize,
+    pub avg_volume: Option<f64>,
+    /// Average of each session's volume relative to its year's mean volume, in percent.
+    pub relative_volume: Option<f64>,
+    pub avg_open_interest: Option<f64>,
+    /// Average month-over-month change in open interest, from first to last session of the month.
+    pub avg_open_interest_change: Option<f64>,
+}
+
+pub(crate) fn mean(values: &[f64]) -> Option<f64> {
+    if values.is_empty() { None } else { Some(values.iter().sum::<f64>() / values.len() as f64) }
+}
+
+/// Per-calendar-month volume and open interest profile.
+pub fn volume_seasonality(input: &[ProcessedBar]) -> Vec<MonthVolumeStats> {
+    let mut rows: Vec<(NaiveDate, Option<f64>, Option<f64>)> = input
+        .iter()
+        .filter_map(|row| Some((row.bar.parsed_date()?, row.bar.volume, row.bar.open_interest)))
+        .collect();
+    rows.sort_by_key(|r| r.0);
+
+    let mut year_volume: HashMap<i32, Vec<f64>> = HashMap::new();
+    for (date, volume, _) in &rows {
+        if let Some(v) = volume {
+            year_volume.entry(date.year()).or_default().push(*v);
+        }
+    }
+    let year_mean: HashMap<i32, f64> = year_volume
+        .into_iter()
+        .filter_map(|(y, v)| mean(&v).map(|m| (y, m)))
+        .collect();
+
+    let mut volumes: [Vec<f64>; 12] = Default::default();
+    let mut relative: [Vec<f64>; 12] = Default::default();
+    let mut open_interest: [Vec<f64>; 12] = Default::default();
+    let mut oi_changes: [Vec<f64>; 12] = Default::default();
+    let mut observations = [0usize; 12];
+    // (year, month) -> (first OI, last OI)
+    let mut oi_bounds: HashMap<(i32, u32), (f64, f64)> = HashMap::new();
+
+    for (date, volume, oi) in &rows {
+        let idx = date.month0() as usize;
+        observations[idx] += 1;
+        if let Some(v) = volume {
+            volumes[idx].push(*v);
+            if let Some(m) = year_mean.get(&date.year()).filter(|m| **m > 0.0) {
+                relative[idx].push(v / m * 100.0);
+            }
+        }
+        if let Some(o) = oi {
+            open_interest[idx].push(*o);
+            oi_bounds
+                .entry((date.year(), date.month()))
+                .and_modify(|b| b.1 = *o)
+                .or_insert((*o, *o));
+        }
+    }
+    for ((_, month), (first, last)) in oi_bounds {
+        oi_changes[(month - 1) as usize].push(last - first);
+    }
+
+    (0..12)
+        .map(|i| MonthVolumeStats {
+            month: i as u32 + 1,
+            observations: observations[i],
+            avg_volume: mean(&volumes[i]),
+            relative_volume: mean(&relative[i]),
+            avg_open_interest: mean(&open_interest[i]),
+            avg_open_interest_change: mean(&oi_changes[i]),
+        })
+        .collect()
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ResamplePeriod {
+    Daily,
+    Weekly,
+    Monthly,
+    Quarterly,
+}
+
+impl ResamplePeriod {
+    fn bucket(self, date: NaiveDate) -> (i32, u32) {
+        match self {
+            ResamplePeriod::Daily => (date.year(), date.ordinal()),
+            ResamplePeriod::Weekly => {
+                let week = date.iso_week();
+                (week.year(), week.week())
+            }
+            ResamplePeriod::Monthly => (date.year(), date.month()),
+            ResamplePeriod::Quarterly => (date.year(), (date.month() - 1) / 3 + 1),
+        }
+    }
+}
+
+/// Aggregates bars into daily/weekly/monthly/quarterly OHLC: first open, max high, min low, last close,
+/// summed volume and last open interest. Each bar is dated on its last session, so intraday input
+/// resampled to `Daily` feeds straight into the daily seasonal pipeline.
+pub fn resample(input: &[ProcessedBar], period: ResamplePeriod) -> Vec<OhlcBar> {
+    struct Bar {
+        bucket: (i32, u32),
+        date: NaiveDate,
+        open: Option<f64>,
+        high: Option<f64>,
+        low: Option<f64>,
+        close: Option<f64>,
+        volume: Option<f64>,
+        open_interest: Option<f64>,
+    }
+
+    let mut rows: Vec<(Timestamp, OhlcBar)> = input
+        .iter()
+        .filter_map(|row| Some((row.bar.timestamp()?, row.bar.clone())))
+        .collect();
+    rows.sort_by_key(|r| r.0.sort_key());
+
+    let mut bars: Vec<Bar> = Vec::new();
+    for (timestamp, OhlcBar { open, high, low, close, volume, open_interest, .. }) in rows {
+        let date = timestamp.date();
+        let bucket = period.bucket(date);
+
+        match bars.last_mut() {
+            Some(bar) if bar.bucket == bucket => {
+                bar.date = date;
+                bar.open = bar.open.or(open);
+                bar.high = match (bar.high, high) {
+                    (Some(a), Some(b)) => Some(a.max(b)),
+                    (a, b) => a.or(b),
+                };
+                bar.low = match (bar.low, low) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    (a, b) => a.or(b),
+                };
+                bar.close = close.or(bar.close);
+                bar.volume = match (bar.volume, volume) {
+                    (Some(a), Some(b)) => Some(a + b),
+                    (a, b) => a.or(b),
+                };
+                bar.open_interest = open_interest.or(bar.open_interest);
+            }
+            _ => bars.push(Bar { bucket, date, open, high, low, close, volume, open_interest }),
+        }
+    }
+
+    bars.into_iter()
+        .map(|b| OhlcBar {
+            date: Some(b.date.format("%Y-%m-%d").to_string()),
+            open: b.open,
+            high: b.high,
+            low: b.low,
+            close: b.close,
+            volume: b.volume,
+            open_interest: b.open_interest,
+            extra: Map::new(),
+        })
+        .collect()
+}
+
+#[derive(Deserialize, Clone)]
+pub struct SessionSpec {
+    pub name: String,
+    /// Local hour the session opens (inclusive).
+    pub start_hour: u32,
+    /// Local hour the session closes (exclusive); may be lower than `start_hour` to wrap midnight.
+    pub end_hour: u32,
+}
+
+impl SessionSpec {
+    fn contains(&self, hour: u32) -> bool {
+        if self.start_hour <= self.end_hour {
+            hour >= self.start_hour && hour < self.end_hour
+        } else {
+            hour >= self.start_hour || hour < self.end_hour
+        }
+    }
+}
+
+pub fn default_sessions() -> Vec<SessionSpec> {
+    [("Overnight", 17, 9), ("Morning", 9, 12), ("Afternoon", 12, 17)]
+        .into_iter()
+        .map(|(name, start_hour, end_hour)| SessionSpec { name: name.to_string(), start_hour, end_hour })
+        .collect()
+}
+
+#[derive(Serialize)]
+pub struct IntradayBucketStats {
+    pub label: String,
+    pub observations: usize,
+    pub avg_return: Option<f64>,
+    pub win_rate: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct IntradaySeasonality {
+    pub by_hour: Vec<IntradayBucketStats>,
+    pub by_session: Vec<IntradayBucketStats>,
+}
+
+fn bucket_stats(label: String, returns: &[f64]) -> IntradayBucketStats {
+    let wins = returns.iter().filter(|r| **r > 0.0).count();
+    IntradayBucketStats {
+        label,
+        observations: returns.len(),
+        avg_return: mean(returns),
+        win_rate: if returns.is_empty() { None } else { Some(wins as f64 / returns.len() as f64 * 100.0) },
+    }
+}
+
+/// Average bar-to-bar % return by local hour of day and by trading session. Only returns between
+/// bars of the same local day are used, so overnight gaps do not land in the first bar's hour.
+pub fn intraday_seasonality(input: &[ProcessedBar], sessions: &[SessionSpec]) -> IntradaySeasonality {
+    let mut bars: Vec<(Timestamp, f64)> = input
+        .iter()
+        .filter_map(|row| {
+            let timestamp = row.bar.timestamp()?;
+            if timestamp.intraday { Some((timestamp, row.bar.close?)) } else { None }
+        })
+        .collect();
+    bars.sort_by_key(|b| b.0.sort_key());
+
+    let mut by_hour: Vec<Vec<f64>> = vec![Vec::new(); 24];
+    let mut by_session: Vec<Vec<f64>> = vec![Vec::new(); sessions.len()];
+    for pair in bars.windows(2) {
+        let ((prev_ts, prev_close), (ts, close)) = (pair[0], pair[1]);
+        if prev_ts.date() != ts.date() || prev_close == 0.0 {
+            continue;
+        }
+        let ret = (close / prev_close - 1.0) * 100.0;
+        let hour = ts.local.hour();
+        by_hour[hour as usize].push(ret);
+        for (i, session) in sessions.iter().enumerate() {
+            if session.contains(hour) {
+                by_session[i].push(ret);
+            }
+        }
+    }
+
+    IntradaySeasonality {
+        by_hour: by_hour
+            .iter()
+            .enumerate()
+            .map(|(h, r)| bucket_stats(format!("{:02}:00", h), r))
+            .collect(),
+        by_session: sessions
+            .iter()
+            .zip(&by_session)
+            .map(|(s, r)| bucket_stats(s.name.clone(), r))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::test_bar as bar;
+
+    /// Four years whose January paths rise 1%, 2%, 3% and 4% a day from the first close.
+    fn bootstrap_input() -> Vec<ProcessedBar> {
+        (1..=4)
+            .flat_map(|k| (1..=5).map(move |day| bar(&format!("{}-01-{:02}", 2019 + k, day), 100.0 * (1.0 + k as f64 * (day - 1) as f64 / 100.0))))
+            .collect()
+    }
+
+    fn assert_normalized(values: &[f64], method: NormalizationMethod, expected: &[f64]) {
+        let got = normalize_values(values, method);
+        assert_eq!(got.len(), expected.len());
+        for (g, e) in got.iter().zip(expected) {
+            assert!((g.is_nan() && e.is_nan()) || (g - e).abs() < 1e-9, "{:?}: {:?} != {:?}", method, got, expected);
+        }
+    }
+
+    #[test]
+    fn min_max_and_winsorized_scale_to_0_100() {
+        assert_normalized(&[2.0, 4.0, f64::NAN, 6.0], NormalizationMethod::MinMax, &[0.0, 50.0, f64::NAN, 100.0]);
+        // The 25th/75th percentiles of 0..40 are 10 and 30; values outside are clipped.
+        let winsorized = NormalizationMethod::WinsorizedMinMax { lower: 25.0, upper: 75.0 };
+        assert_normalized(&[0.0, 10.0, 20.0, 30.0, 40.0], winsorized, &[0.0, 0.0, 50.0, 100.0, 100.0]);
+        // Interpolated percentile: the 10th of 0..40 is 4.
+        let interpolated = NormalizationMethod::WinsorizedMinMax { lower: 10.0, upper: 90.0 };
+        assert_normalized(&[0.0, 10.0, 20.0, 30.0, 40.0], interpolated, &[0.0, 18.75, 50.0, 81.25, 100.0]);
+    }
+
+    #[test]
+    fn percentile_rank_gives_ties_their_average_rank() {
+        assert_normalized(&[30.0, 10.0, 20.0, 20.0], NormalizationMethod::PercentileRank, &[100.0, 0.0, 50.0, 50.0]);
+        assert_normalized(&[1.0, 1.0, 2.0], NormalizationMethod::PercentileRank, &[25.0, 25.0, 100.0]);
+        assert_normalized(&[5.0, f64::NAN], NormalizationMethod::PercentileRank, &[f64::NAN, f64::NAN]);
+    }
+
+    #[test]
+    fn z_score_uses_the_population_deviation() {
+        let values = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        assert_normalized(&values, NormalizationMethod::ZScore, &[-1.5, -0.5, -0.5, -0.5, 0.0, 0.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn constant_series_has_no_scale() {
+        let constant = [3.0, 3.0, 3.0];
+        let none = [f64::NAN; 3];
+        assert_normalized(&constant, NormalizationMethod::MinMax, &none);
+        assert_normalized(&constant, NormalizationMethod::ZScore, &none);
+        assert_normalized(&constant, NormalizationMethod::WinsorizedMinMax { lower: 5.0, upper: 95.0 }, &none);
+        // Every value ties with every other, so all sit at the middle rank.
+        assert_normalized(&constant, NormalizationMethod::PercentileRank, &[50.0; 3]);
+        assert_normalized(&[], NormalizationMethod::MinMax, &[]);
+    }
+
+    #[test]
+    fn trading_day_basis_aligns_years_on_their_sessions() {
+        // 2024 opens on Tuesday Jan 2 and 2025 on Thursday Jan 2; both line up on sessions 1 and 2.
+        let mut input = vec![bar("2024-01-02", 100.0), bar("2024-01-03", 110.0), bar("2025-01-02", 50.0), bar("2025-01-06", 60.0)];
+        for (row, day) in input.iter_mut().zip([1, 2, 1, 2]) {
+            row.trading_day_no = Some(day);
+        }
+        input.push(bar("2025-01-04", 70.0));
+        let bands = seasonal_bootstrap(&input, CurveBasis::TradingDay, 50, 0.9, 1);
+        assert_eq!(bands.years, vec![2024, 2025]);
+        let means: Vec<(u32, f64)> = bands.points.iter().map(|p| (p.day_of_year, p.mean)).collect();
+        assert_eq!(means.len(), 2);
+        assert_eq!(means[0], (1, 0.0));
+        assert_eq!(means[1].0, 2);
+        assert!((means[1].1 - 15.0).abs() < 1e-9, "{:?}", means);
+        // Without a calendar there is nothing to align on.
+        assert!(seasonal_bootstrap(&bootstrap_input(), CurveBasis::TradingDay, 50, 0.9, 1).points.is_empty());
+    }
+
+    #[test]
+    fn seasonal_bootstrap_is_pinned_by_its_seed() {
+        let bands = seasonal_bootstrap(&bootstrap_input(), CurveBasis::YtdChange, 200, 0.9, 7);
+        assert_eq!(bands.years, vec![2020, 2021, 2022, 2023]);
+        let day5 = &bands.points[4];
+        assert_eq!(day5.day_of_year, 5);
+        assert!((day5.mean - 10.0).abs() < 1e-9);
+        let pinned = (day5.lower, day5.upper);
+        assert!((pinned.0 - 6.0).abs() < 1e-9 && (pinned.1 - 13.05).abs() < 1e-9, "{:?}", pinned);
+
+        let again = seasonal_bootstrap(&bootstrap_input(), CurveBasis::YtdChange, 200, 0.9, 7);
+        assert_eq!((again.points[4].lower, again.points[4].upper), pinned);
+        let other = seasonal_bootstrap(&bootstrap_input(), CurveBasis::YtdChange, 200, 0.9, 8);
+        assert_ne!((other.points[4].lower, other.points[4].upper), pinned);
+    }
+
+    fn ohlcv(date: &str, open: f64, high: f64, low: f64, close: f64, volume: f64) -> ProcessedBar {
+        let mut row = bar(date, close);
+        (row.bar.open, row.bar.high, row.bar.low, row.bar.volume) = (Some(open), Some(high), Some(low), Some(volume));
+        row
+    }
+
+    #[test]
+    fn resample_aggregates_each_period() {
+        let input = [
+            ohlcv("2024-01-02T16:00:00", 12.0, 14.0, 11.0, 13.0, 200.0),
+            ohlcv("2024-01-02T10:00:00", 10.0, 12.0, 9.0, 11.0, 100.0),
+            ohlcv("2024-01-31", 13.0, 20.0, 12.0, 19.0, 50.0),
+            ohlcv("2024-02-01", 19.0, 19.5, 8.0, 9.0, 10.0),
+        ];
+        let summary = |b: &OhlcBar| (b.date.clone().unwrap(), b.open, b.high, b.low, b.close, b.volume);
+
+        let daily: Vec<_> = resample(&input, ResamplePeriod::Daily).iter().map(summary).collect();
+        assert_eq!(daily[0], ("2024-01-02".into(), Some(10.0), Some(14.0), Some(9.0), Some(13.0), Some(300.0)));
+        assert_eq!(daily.len(), 3);
+
+        let monthly: Vec<_> = resample(&input, ResamplePeriod::Monthly).iter().map(summary).collect();
+        assert_eq!(
+            monthly,
+            vec![
+                ("2024-01-31".into(), Some(10.0), Some(20.0), Some(9.0), Some(19.0), Some(350.0)),
+                ("2024-02-01".into(), Some(19.0), Some(19.5), Some(8.0), Some(9.0), Some(10.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn resample_skips_missing_values_within_a_period() {
+        let mut first = ohlcv("2024-04-01", 1.0, 2.0, 0.5, 1.5, 10.0);
+        first.bar.volume = None;
+        let mut last = ohlcv("2024-06-28", 3.0, 4.0, 2.5, 3.5, 30.0);
+        last.bar.close = None;
+        let quarter = resample(&[first, last], ResamplePeriod::Quarterly);
+        assert_eq!(quarter.len(), 1);
+        let q = &quarter[0];
+        assert_eq!((q.date.as_deref(), q.open, q.high, q.low, q.close, q.volume), (Some("2024-06-28"), Some(1.0), Some(4.0), Some(0.5), Some(1.5), Some(30.0)));
+    }
+
+    /// January closes starting at `start`, one per day from Jan 1.
+    fn january(year: i32, start: f64, closes: &[f64]) -> Vec<ProcessedBar> {
+        closes.iter().enumerate().map(|(i, c)| bar(&format!("{}-01-{:02}", year, i + 1), start * c)).collect()
+    }
+
+    fn analog_input() -> Vec<ProcessedBar> {
+        let rising: Vec<f64> = (0..10).map(|d| 1.0 + d as f64 / 100.0).collect();
+        let falling: Vec<f64> = (0..10).map(|d| 1.0 - d as f64 / 100.0).collect();
+        let zigzag = [1.0, 1.03, 0.99, 1.04, 0.98, 1.05, 0.97, 1.06, 0.96, 1.07];
+        let current: Vec<f64> = (0..5).map(|d| 1.0 + 2.0 * d as f64 / 100.0).collect();
+        [january(2021, 100.0, &rising), january(2022, 100.0, &falling), january(2023, 100.0, &zigzag), january(2024, 50.0, &current)].concat()
+    }
+
+    #[test]
+    fn analog_years_rank_by_path_correlation() {
+        let result = find_analog_years(&analog_input(), 3);
+        assert_eq!(result.current_year, 2024);
+        assert_eq!(result.current_path.len(), 5);
+        let ranked: Vec<i32> = result.analogs.iter().map(|a| a.year).collect();
+        assert_eq!(ranked, vec![2021, 2023, 2022]);
+        assert!((result.analogs[0].correlation - 1.0).abs() < 1e-9);
+        assert!((result.analogs[2].correlation + 1.0).abs() < 1e-9);
+        // The zigzag's first five days (0, 3, -1, 4, -2) against a straight line.
+        let expected = -3.0 / (10.0_f64 * 26.8).sqrt();
+        assert!((result.analogs[1].correlation - expected).abs() < 1e-9, "{}", result.analogs[1].correlation);
+        // Changes are read at the current year's last day and at the end of the analog year.
+        assert!((result.analogs[0].ytd_change - 4.0).abs() < 1e-9);
+        assert!((result.analogs[0].full_year_change - 9.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn analog_years_keep_the_top_n_and_carry_missing_days_forward() {
+        let mut input = analog_input();
+        // Without Jan 3, 2021 reads Jan 2's value there and no longer tracks the current year exactly.
+        input.retain(|r| r.bar.date.as_deref() != Some("2021-01-03"));
+        let result = find_analog_years(&input, 1);
+        assert_eq!(result.analogs.len(), 1);
+        assert_eq!(result.analogs[0].year, 2021);
+        assert!(result.analogs[0].correlation < 1.0);
+        assert!(find_analog_years(&[], 5).analogs.is_empty());
+    }
+
+    fn with_volume(date: &str, volume: Option<f64>, open_interest: f64) -> ProcessedBar {
+        let mut row = bar(date, 10.0);
+        (row.bar.volume, row.bar.open_interest) = (volume, Some(open_interest));
+        row
+    }
+
+    #[test]
+    fn volume_seasonality_profiles_each_month() {
+        // Out of order on purpose: open interest changes run from the first to the last session by date.
+        let input = [
+            with_volume("2023-01-03", Some(300.0), 1200.0),
+            with_volume("2023-01-02", Some(100.0), 1000.0),
+            with_volume("2023-02-01", Some(200.0), 1100.0),
+            with_volume("2024-01-02", Some(400.0), 500.0),
+            with_volume("2024-01-03", None, 800.0),
+        ];
+        let stats = volume_seasonality(&input);
+        assert_eq!(stats.len(), 12);
+
+        let jan = &stats[0];
+        assert_eq!((jan.month, jan.observations), (1, 4));
+        assert!((jan.avg_volume.unwrap() - 800.0 / 3.0).abs() < 1e-9);
+        // 2023 averages 200 a session, 2024 averages 400: Jan sessions sit at 50%, 150% and 100%.
+        assert!((jan.relative_volume.unwrap() - 100.0).abs() < 1e-9);
+        assert_eq!(jan.avg_open_interest, Some(875.0));
+        assert_eq!(jan.avg_open_interest_change, Some(250.0));
+
+        let feb = &stats[1];
+        assert_eq!((feb.observations, feb.avg_volume, feb.relative_volume), (1, Some(200.0), Some(100.0)));
+        assert_eq!(feb.avg_open_interest_change, Some(0.0));
+
+        let mar = &stats[2];
+        assert_eq!((mar.observations, mar.avg_volume, mar.avg_open_interest), (0, None, None));
+    }
+
+    /// Past Januaries flat at 100 to Jan 3 and at `100 * ratio` afterwards; 2024 ends on Wednesday Jan 3 at 200.
+    fn forecast_input() -> Vec<ProcessedBar> {
+        let mut input = Vec::new();
+        for (year, ratio) in [(2021, 1.3), (2022, 1.1), (2023, 0.9)] {
+            input.extend((1..=10).map(|d| bar(&format!("{}-01-{:02}", year, d), if d <= 3 { 100.0 } else { 100.0 * ratio })));
+        }
+        input.extend((1..=3).map(|d| bar(&format!("2024-01-{:02}", d), 200.0)));
+        input
+    }
+
+    #[test]
+    fn forecast_projects_past_moves_from_the_as_of_day() {
+        let forecast = seasonal_forecast(&forecast_input(), &[0, 2, 10], &[0.0, 50.0, 100.0]).unwrap();
+        assert_eq!((forecast.as_of.as_str(), forecast.last_close), ("2024-01-03", 200.0));
+
+        let all = &forecast.lookbacks[0];
+        assert_eq!(all.years_used, vec![2021, 2022, 2023]);
+        let first = &all.path[0];
+        assert_eq!((first.date.as_str(), first.day_of_year), ("2024-01-04", 4));
+        assert!((first.projected - 220.0).abs() < 1e-9);
+        let bands: Vec<f64> = first.bands.iter().map(|b| (b * 1e6).round() / 1e6).collect();
+        assert_eq!(bands, vec![180.0, 220.0, 260.0]);
+
+        // The most recent years only; a lookback past the history uses all of it.
+        let recent = &forecast.lookbacks[1];
+        assert_eq!(recent.years_used, vec![2022, 2023]);
+        assert!((recent.path[0].projected - 200.0).abs() < 1e-9);
+        assert_eq!(forecast.lookbacks[2].years_used, vec![2021, 2022, 2023]);
+    }
+
+    #[test]
+    fn forecast_skips_weekends_and_runs_to_the_year_end() {
+        let forecast = seasonal_forecast(&forecast_input(), &[0], &[]).unwrap();
+        let path = &forecast.lookbacks[0].path;
+        let dates: Vec<&str> = path.iter().take(3).map(|p| p.date.as_str()).collect();
+        assert_eq!(dates, vec!["2024-01-04", "2024-01-05", "2024-01-08"]);
+        assert_eq!(path.last().map(|p| p.date.as_str()), Some("2024-12-31"));
+        assert!(path.iter().all(|p| p.bands.is_empty()));
+        assert!(seasonal_forecast(&[], &[0], &[50.0]).is_none());
+    }
+
+    #[test]
+    fn close_series_keeps_the_last_bar_of_each_day() {
+        let input = [bar("2024-01-02T10:00:00", 1.0), bar("2024-01-02T16:00:00", 2.0), bar("2024-01-01", 5.0)];
+        let day = |d: u32| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+        assert_eq!(close_series(&input), vec![(day(1), 5.0), (day(2), 2.0)]);
+    }
+
+    /// Three daily bars; the middle one has no prices at all.
+    fn with_missing_bar() -> Vec<OhlcBar> {
+        let mut bars = vec![bar("2024-01-02", 10.0).bar, bar("2024-01-03", 11.0).bar, bar("2024-01-04", 12.0).bar];
+        bars[1] = OhlcBar { date: bars[1].date.clone(), ..Default::default() };
+        bars
+    }
+
+    fn with_policy(policy: NanPolicy) -> ProcessingOptions {
+        ProcessingOptions { nan_policy: Some(policy), ..Default::default() }
+    }
+
+    /// Stores `bars` in a scratch database and reads them back as processed and as source bars.
+    fn store_and_reload(name: &str, bars: &[ProcessedBar]) -> (Vec<ProcessedBar>, Vec<OhlcBar>) {
+        let path = std::env::temp_dir().join(format!("seasonality-{}-{}.db", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        crate::db::ensure_schema(&path).unwrap();
+        let conn = rusqlite::Connection::open(&path).unwrap();
+        crate::db::insert_bars(&conn, bars, "T", &[]).unwrap();
+        let mut stmt = conn.prepare("SELECT * FROM asset_data ORDER BY id").unwrap();
+        let processed = stmt.query_map([], crate::db::read_bar).unwrap().map(|r| r.unwrap()).collect();
+        let source = stmt.query_map([], crate::db::read_source_bar).unwrap().map(|r| r.unwrap().1).collect();
+        drop(stmt);
+        drop(conn);
+        let _ = std::fs::remove_file(&path);
+        (processed, source)
+    }
+
+    #[test]
+    fn missing_values_survive_a_store_and_reload() {
+        let processed = calculate_derived_columns(&with_missing_bar(), false, &with_policy(NanPolicy::Null));
+        let (reloaded, source) = store_and_reload("null", &processed);
+        assert_eq!(reloaded[1].bar.close, None);
+        assert_eq!(reloaded[1].bar.open, None);
+        assert_eq!(reloaded[1].pct_change, None);
+        assert_eq!(reloaded[2].pct_change, None);
+        assert_eq!(source, with_missing_bar());
+    }
+
+    #[test]
+    fn policies_leave_the_source_bars_unchanged() {
+        for policy in [NanPolicy::Zero, NanPolicy::ForwardFill, NanPolicy::Exclude] {
+            let processed = calculate_derived_columns(&with_missing_bar(), false, &with_policy(policy));
+            let (_, source) = store_and_reload(policy.describe(), &processed);
+            assert_eq!(source, with_missing_bar(), "{:?}", policy);
+            // Reprocessing what was stored gives the same result: nothing was baked into the source.
+            assert_eq!(calculate_derived_columns(&source, false, &with_policy(policy)), processed, "{:?}", policy);
+        }
+    }
+
+    #[test]
+    fn merged_options_keep_fields_not_sent() {
+        let stored = ProcessingOptions {
+            detrend: Some(DetrendMethod::LinearPerYear),
+            fill_policy: FillPolicy::ForwardFill,
+            outliers: Some(serde_json::from_value(serde_json::json!({})).unwrap()),
+            ..Default::default()
+        };
+        let changes = serde_json::json!({ "nan_policy": "zero", "outliers": null });
+        let merged = stored.merged(changes.as_object().unwrap().clone()).unwrap();
+        assert_eq!(merged, ProcessingOptions { nan_policy: Some(NanPolicy::Zero), outliers: None, ..stored });
+    }
+
+    #[test]
+    fn policies_decide_the_derived_values() {
+        let changes = |policy| -> Vec<Option<f64>> {
+            calculate_derived_columns(&with_missing_bar(), false, &with_policy(policy)).iter().map(|b| b.pct_change).collect()
+        };
+        assert_eq!(changes(NanPolicy::Null), vec![None, None, None]);
+        assert_eq!(changes(NanPolicy::Zero), vec![Some(0.0), Some(0.0), Some(0.0)]);
+        assert_eq!(changes(NanPolicy::ForwardFill), vec![None, Some(0.0), Some(2.0)]);
+        assert_eq!(changes(NanPolicy::Exclude), vec![None, None, Some(2.0)]);
+
+        let excluded = calculate_derived_columns(&with_missing_bar(), false, &with_policy(NanPolicy::Exclude));
+        assert_eq!(excluded.len(), 3);
+        assert_eq!((excluded[1].normalized, excluded[1].month_no), (None, Some(1)));
+    }
+}
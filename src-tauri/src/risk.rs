@@ -0,0 +1,161 @@
+use crate::model::ProcessedBar;
+use crate::processor::mean;
+use chrono::{Datelike, NaiveDate};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Risk profile of one calendar month across all years with data. Percent units throughout.
+#[derive(Serialize)]
+pub struct MonthRiskStats {
+    pub month: u32,
+    pub years: usize,
+    /// Mean month return, from the previous month's last close to this month's last close.
+    pub avg_return_pct: Option<f64>,
+    pub win_rate: Option<f64>,
+    /// Mean of each year's deepest close-to-close drawdown inside the month.
+    pub avg_max_drawdown_pct: Option<f64>,
+    pub worst_drawdown_pct: Option<f64>,
+    /// Mean true range in price units.
+    pub avg_atr: Option<f64>,
+    /// Mean true range relative to the close.
+    pub avg_atr_pct: Option<f64>,
+    /// Annualized standard deviation of daily log returns (252 sessions).
+    pub realized_vol_pct: Option<f64>,
+}
+
+struct Bar {
+    high: Option<f64>,
+    low: Option<f64>,
+    close: f64,
+}
+
+pub fn monthly_risk(input: &[ProcessedBar]) -> Vec<MonthRiskStats> {
+    let bars: BTreeMap<NaiveDate, Bar> = input
+        .iter()
+        .filter_map(|row| {
+            let close = row.bar.close.filter(|c| *c > 0.0)?;
+            Some((row.bar.parsed_date()?, Bar { high: row.bar.high, low: row.bar.low, close }))
+        })
+        .collect();
+
+    #[derive(Default)]
+    struct MonthAcc {
+        start: Option<f64>,
+        end: f64,
+        peak: f64,
+        drawdown: f64,
+        ranges: Vec<f64>,
+        ranges_pct: Vec<f64>,
+        log_returns: Vec<f64>,
+    }
+    let mut months: BTreeMap<(i32, u32), MonthAcc> = BTreeMap::new();
+    let mut prev: Option<f64> = None;
+    for (date, bar) in &bars {
+        let acc = months.entry((date.year(), date.month())).or_insert_with(|| MonthAcc {
+            start: prev,
+            peak: prev.unwrap_or(bar.close),
+            ..Default::default()
+        });
+        acc.peak = acc.peak.max(bar.close);
+        acc.drawdown = acc.drawdown.min((bar.close / acc.peak - 1.0) * 100.0);
+        acc.end = bar.close;
+
+        if let (Some(h), Some(l)) = (bar.high, bar.low) {
+            let range = match prev {
+                Some(p) => (h - l).max((h - p).abs()).max((l - p).abs()),
+                None => h - l,
+            };
+            acc.ranges.push(range);
+            acc.ranges_pct.push(range / bar.close * 100.0);
+        }
+        if let Some(p) = prev {
+            acc.log_returns.push((bar.close / p).ln());
+        }
+        prev = Some(bar.close);
+    }
+
+    (1..=12)
+        .map(|month| {
+            let accs: Vec<&MonthAcc> = months.iter().filter(|((_, m), _)| *m == month).map(|(_, a)| a).collect();
+            let returns: Vec<f64> = accs.iter().filter_map(|a| a.start.map(|s| (a.end / s - 1.0) * 100.0)).collect();
+            let drawdowns: Vec<f64> = accs.iter().map(|a| a.drawdown).collect();
+            let ranges: Vec<f64> = accs.iter().flat_map(|a| a.ranges.iter().copied()).collect();
+            let ranges_pct: Vec<f64> = accs.iter().flat_map(|a| a.ranges_pct.iter().copied()).collect();
+            let log_returns: Vec<f64> = accs.iter().flat_map(|a| a.log_returns.iter().copied()).collect();
+            let realized_vol_pct = mean(&log_returns).filter(|_| log_returns.len() > 1).map(|m| {
+                let var = log_returns.iter().map(|r| (r - m).powi(2)).sum::<f64>() / (log_returns.len() - 1) as f64;
+                var.sqrt() * 252f64.sqrt() * 100.0
+            });
+            MonthRiskStats {
+                month,
+                years: accs.len(),
+                avg_return_pct: mean(&returns),
+                win_rate: if returns.is_empty() { None } else { Some(returns.iter().filter(|r| **r > 0.0).count() as f64 / returns.len() as f64 * 100.0) },
+                avg_max_drawdown_pct: mean(&drawdowns),
+                worst_drawdown_pct: drawdowns.iter().copied().reduce(f64::min),
+                avg_atr: mean(&ranges),
+                avg_atr_pct: mean(&ranges_pct),
+                realized_vol_pct,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::test_bar;
+
+    fn bar(date: &str, high: Option<f64>, low: Option<f64>, close: f64) -> ProcessedBar {
+        let mut bar = test_bar(date, close);
+        (bar.bar.high, bar.bar.low) = (high, low);
+        bar
+    }
+
+    fn close_to(a: Option<f64>, b: f64) -> bool {
+        a.is_some_and(|a| (a - b).abs() < 1e-9)
+    }
+
+    fn stats() -> Vec<MonthRiskStats> {
+        monthly_risk(&[
+            bar("2024-01-30", Some(11.0), Some(9.0), 10.0),
+            bar("2024-01-31", Some(12.0), Some(10.0), 11.0),
+            bar("2024-02-01", Some(11.0), Some(9.5), 10.0),
+            // Gaps up: the true range reaches back to the previous close.
+            bar("2024-02-02", Some(14.0), Some(13.0), 13.2),
+            bar("2024-02-05", Some(13.5), Some(12.0), 11.88),
+            bar("2024-02-06", None, None, 0.0),
+            bar("2025-02-03", None, None, 10.692),
+        ])
+    }
+
+    #[test]
+    fn drawdown_is_measured_from_the_running_peak() {
+        let feb = &stats()[1];
+        assert_eq!(feb.years, 2);
+        // 2024: the peak of 13.2 falls to 11.88; 2025: the prior close of 11.88 seeds the peak.
+        assert!(close_to(feb.worst_drawdown_pct, -10.0));
+        assert!(close_to(feb.avg_max_drawdown_pct, -10.0));
+        assert!(close_to(feb.avg_return_pct, -1.0));
+        assert!(close_to(feb.win_rate, 50.0));
+    }
+
+    #[test]
+    fn true_range_includes_gaps_from_the_previous_close() {
+        let stats = stats();
+        assert!(close_to(stats[0].avg_atr, 2.0));
+        assert!(close_to(stats[1].avg_atr, (1.5 + 4.0 + 1.5) / 3.0));
+        assert!(close_to(stats[1].avg_atr_pct, (1.5 / 10.0 + 4.0 / 13.2 + 1.5 / 11.88) / 3.0 * 100.0));
+    }
+
+    #[test]
+    fn months_without_history_have_no_return() {
+        let stats = stats();
+        assert_eq!(stats.len(), 12);
+        assert_eq!(stats[0].years, 1);
+        assert_eq!(stats[0].avg_return_pct, None);
+        assert_eq!(stats[0].avg_max_drawdown_pct, Some(0.0));
+        assert_eq!(stats[2].years, 0);
+        assert!(stats[2].avg_max_drawdown_pct.is_none() && stats[2].realized_vol_pct.is_none());
+    }
+}
@@ -0,0 +1,290 @@
+use crate::model::{OhlcBar, ProcessedBar};
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ContractSpec {
+    /// Stored asset holding this contract's bars.
+    pub asset: String,
+    /// Last trading day (YYYY-MM-DD); contracts are chained in expiry order.
+    pub expiry: String,
+}
+
+/// When the continuous series moves from the front contract to the next one.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RollRule {
+    /// Roll on the first session on or after `days` calendar days before expiry.
+    DaysBeforeExpiry { days: u32 },
+    /// Roll once the next contract trades more volume than the front contract.
+    Volume,
+    /// Roll once the next contract has more open interest than the front contract.
+    OpenInterest,
+}
+
+/// How earlier contracts are shifted so the series has no jump at each roll.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RollAdjustment {
+    /// Add the price gap at each roll to all earlier bars.
+    #[default]
+    BackAdjusted,
+    /// Multiply earlier bars by the price ratio at each roll; keeps percentage returns intact.
+    RatioAdjusted,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RollEvent {
+    pub date: String,
+    pub from: String,
+    pub to: String,
+    pub from_close: f64,
+    pub to_close: f64,
+}
+
+/// The chained bars (`OhlcBar` as built, `ProcessedBar` once stored) and where the series rolled.
+#[derive(Serialize)]
+pub struct ContinuousSeries<B = OhlcBar> {
+    pub rows: Vec<B>,
+    pub rolls: Vec<RollEvent>,
+}
+
+#[derive(Clone, Copy)]
+struct Bar {
+    open: Option<f64>,
+    high: Option<f64>,
+    low: Option<f64>,
+    close: f64,
+    volume: Option<f64>,
+    open_interest: Option<f64>,
+}
+
+struct Contract {
+    asset: String,
+    expiry: NaiveDate,
+    bars: BTreeMap<NaiveDate, Bar>,
+}
+
+impl Contract {
+    fn close_on_or_before(&self, date: NaiveDate) -> Option<f64> {
+        self.bars.range(..=date).next_back().map(|(_, b)| b.close)
+    }
+}
+
+fn bars(input: &[ProcessedBar]) -> BTreeMap<NaiveDate, Bar> {
+    input
+        .iter()
+        .filter_map(|row| {
+            let bar = &row.bar;
+            Some((
+                bar.parsed_date()?,
+                Bar {
+                    open: bar.open,
+                    high: bar.high,
+                    low: bar.low,
+                    close: bar.close?,
+                    volume: bar.volume,
+                    open_interest: bar.open_interest,
+                },
+            ))
+        })
+        .collect()
+}
+
+fn should_roll(rule: RollRule, date: NaiveDate, front: &Contract, next: &Contract) -> bool {
+    let Some(next_bar) = next.bars.get(&date) else { return false };
+    // The front contract has stopped trading: roll regardless of the rule.
+    if date > front.expiry || front.bars.range(date..).next().is_none() {
+        return true;
+    }
+    let front_bar = front.bars.get(&date);
+    match rule {
+        RollRule::DaysBeforeExpiry { days } => date >= front.expiry - Duration::days(days as i64),
+        RollRule::Volume => match (next_bar.volume, front_bar.and_then(|b| b.volume)) {
+            (Some(n), Some(f)) => n > f,
+            _ => false,
+        },
+        RollRule::OpenInterest => match (next_bar.open_interest, front_bar.and_then(|b| b.open_interest)) {
+            (Some(n), Some(f)) => n > f,
+            _ => false,
+        },
+    }
+}
+
+/// Chains contract bars into one continuous series, adjusted back from the most recent contract.
+/// Each row carries the source contract in a "Contract" column.
+pub fn build_continuous(
+    contracts: &[(ContractSpec, Vec<ProcessedBar>)],
+    rule: RollRule,
+    adjustment: RollAdjustment,
+) -> anyhow::Result<ContinuousSeries> {
+    if contracts.len() < 2 {
+        return Err(anyhow::anyhow!("A continuous series needs at least two contracts"));
+    }
+    let mut chain: Vec<Contract> = contracts
+        .iter()
+        .map(|(spec, rows)| {
+            let expiry = NaiveDate::parse_from_str(&spec.expiry, "%Y-%m-%d")
+                .map_err(|_| anyhow::anyhow!("Invalid expiry '{}' for '{}', expected YYYY-MM-DD", spec.expiry, spec.asset))?;
+            let bars = bars(rows);
+            if bars.is_empty() {
+                return Err(anyhow::anyhow!("Contract '{}' has no bars with a close", spec.asset));
+            }
+            Ok(Contract { asset: spec.asset.clone(), expiry, bars })
+        })
+        .collect::<anyhow::Result<_>>()?;
+    chain.sort_by_key(|c| c.expiry);
+
+    let dates: BTreeSet<NaiveDate> = chain.iter().flat_map(|c| c.bars.keys().copied()).collect();
+    let mut active = 0;
+    let mut rolls: Vec<RollEvent> = Vec::new();
+    // (date, contract index, bar)
+    let mut picked: Vec<(NaiveDate, usize, Bar)> = Vec::new();
+
+    for date in dates {
+        while active + 1 < chain.len() && should_roll(rule, date, &chain[active], &chain[active + 1]) {
+            let (front, next) = (&chain[active], &chain[active + 1]);
+            if let Some(from_close) = front.close_on_or_before(date) {
+                rolls.push(RollEvent {
+                    date: date.format("%Y-%m-%d").to_string(),
+                    from: front.asset.clone(),
+                    to: next.asset.clone(),
+                    from_close,
+                    to_close: next.bars[&date].close,
+                });
+            }
+            active += 1;
+        }
+        if let Some(bar) = chain[active].bars.get(&date) {
+            picked.push((date, active, *bar));
+        }
+    }
+
+    // Cumulative adjustment applied to bars of each contract, from the latest contract backwards.
+    let mut offsets = vec![0.0; chain.len()];
+    let mut ratios = vec![1.0; chain.len()];
+    for i in (0..chain.len().saturating_sub(1)).rev() {
+        offsets[i] = offsets[i + 1];
+        ratios[i] = ratios[i + 1];
+        if let Some(roll) = rolls.iter().find(|r| r.from == chain[i].asset && r.to == chain[i + 1].asset) {
+            offsets[i] += roll.to_close - roll.from_close;
+            if roll.from_close != 0.0 {
+                ratios[i] *= roll.to_close / roll.from_close;
+            }
+        }
+    }
+
+    let rows = picked
+        .into_iter()
+        .map(|(date, idx, bar)| {
+            let adjust = |v: f64| match adjustment {
+                RollAdjustment::BackAdjusted => v + offsets[idx],
+                RollAdjustment::RatioAdjusted => v * ratios[idx],
+            };
+            OhlcBar {
+                date: Some(date.format("%Y-%m-%d").to_string()),
+                open: bar.open.map(adjust),
+                high: bar.high.map(adjust),
+                low: bar.low.map(adjust),
+                close: Some(adjust(bar.close)),
+                volume: bar.volume,
+                open_interest: bar.open_interest,
+                extra: Map::from_iter([("Contract".to_string(), Value::String(chain[idx].asset.clone()))]),
+            }
+        })
+        .collect();
+
+    Ok(ContinuousSeries { rows, rolls })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::test_bar;
+
+    /// Daily bars from `first` to `last` inclusive at a flat close, with volume from `volume(day index)`.
+    fn contract(asset: &str, expiry: &str, first: &str, last: &str, close: f64, volume: impl Fn(i64) -> f64) -> (ContractSpec, Vec<ProcessedBar>) {
+        let first = NaiveDate::parse_from_str(first, "%Y-%m-%d").unwrap();
+        let last = NaiveDate::parse_from_str(last, "%Y-%m-%d").unwrap();
+        let rows = (0..=(last - first).num_days())
+            .map(|i| {
+                let mut bar = test_bar(&(first + Duration::days(i)).format("%Y-%m-%d").to_string(), close);
+                (bar.bar.high, bar.bar.volume) = (Some(close + 1.0), Some(volume(i)));
+                bar
+            })
+            .collect();
+        (ContractSpec { asset: asset.to_string(), expiry: expiry.to_string() }, rows)
+    }
+
+    fn chain() -> Vec<(ContractSpec, Vec<ProcessedBar>)> {
+        // Listed out of expiry order on purpose.
+        vec![
+            contract("CLH24", "2024-03-10", "2024-01-20", "2024-03-10", 121.0, |_| 10.0),
+            contract("CLF24", "2024-01-10", "2024-01-02", "2024-01-10", 100.0, |_| 10.0),
+            contract("CLG24", "2024-02-10", "2024-01-02", "2024-02-10", 110.0, |_| 10.0),
+        ]
+    }
+
+    fn close_on<'a>(series: &'a ContinuousSeries, date: &str) -> (Option<f64>, Option<&'a Value>) {
+        let row = series.rows.iter().find(|r| r.date.as_deref() == Some(date)).unwrap();
+        (row.close, row.extra.get("Contract"))
+    }
+
+    #[test]
+    fn rolls_days_before_expiry_and_back_adjusts() {
+        let series = build_continuous(&chain(), RollRule::DaysBeforeExpiry { days: 3 }, RollAdjustment::BackAdjusted).unwrap();
+        let rolls: Vec<(&str, &str, &str)> = series.rolls.iter().map(|r| (r.date.as_str(), r.from.as_str(), r.to.as_str())).collect();
+        assert_eq!(rolls, vec![("2024-01-07", "CLF24", "CLG24"), ("2024-02-07", "CLG24", "CLH24")]);
+        // Every contract is shifted onto the latest one's level, so the flat prices chain without a jump.
+        assert!(series.rows.iter().all(|r| r.close == Some(121.0) && r.high == Some(122.0)));
+        assert_eq!(close_on(&series, "2024-01-06").1, Some(&Value::from("CLF24")));
+        assert_eq!(close_on(&series, "2024-01-07").1, Some(&Value::from("CLG24")));
+        assert_eq!(close_on(&series, "2024-03-10").1, Some(&Value::from("CLH24")));
+        assert_eq!(series.rows.len(), 69);
+    }
+
+    #[test]
+    fn ratio_adjustment_compounds_across_rolls() {
+        let series = build_continuous(&chain(), RollRule::DaysBeforeExpiry { days: 3 }, RollAdjustment::RatioAdjusted).unwrap();
+        for date in ["2024-01-02", "2024-01-20", "2024-03-01"] {
+            assert!((close_on(&series, date).0.unwrap() - 121.0).abs() < 1e-9, "{}", date);
+        }
+        let first = &series.rows[0];
+        assert!((first.high.unwrap() - 101.0 * 1.21).abs() < 1e-9);
+    }
+
+    #[test]
+    fn volume_rule_rolls_when_the_next_contract_trades_more() {
+        let contracts = vec![
+            contract("A", "2024-01-10", "2024-01-02", "2024-01-10", 100.0, |_| 50.0),
+            contract("B", "2024-02-10", "2024-01-02", "2024-01-20", 104.0, |i| 10.0 * i as f64),
+        ];
+        let series = build_continuous(&contracts, RollRule::Volume, RollAdjustment::BackAdjusted).unwrap();
+        assert_eq!(series.rolls.len(), 1);
+        assert_eq!(series.rolls[0].date, "2024-01-08");
+        assert_eq!((series.rolls[0].from_close, series.rolls[0].to_close), (100.0, 104.0));
+        assert_eq!(close_on(&series, "2024-01-07"), (Some(104.0), Some(&Value::from("A"))));
+    }
+
+    #[test]
+    fn expired_front_contract_forces_a_roll() {
+        let contracts = vec![
+            contract("A", "2024-01-05", "2024-01-02", "2024-01-05", 100.0, |_| 50.0),
+            contract("B", "2024-02-10", "2024-01-02", "2024-01-20", 90.0, |_| 1.0),
+        ];
+        let series = build_continuous(&contracts, RollRule::Volume, RollAdjustment::BackAdjusted).unwrap();
+        assert_eq!(series.rolls[0].date, "2024-01-06");
+        assert_eq!(close_on(&series, "2024-01-05").0, Some(90.0));
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        let one = vec![contract("A", "2024-01-05", "2024-01-02", "2024-01-05", 100.0, |_| 1.0)];
+        assert!(build_continuous(&one, RollRule::Volume, RollAdjustment::BackAdjusted).is_err());
+        let mut two = chain();
+        two[0].0.expiry = "03/10/2024".to_string();
+        assert!(build_continuous(&two, RollRule::Volume, RollAdjustment::BackAdjusted).is_err());
+    }
+}
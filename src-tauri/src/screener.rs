@@ -0,0 +1,406 @@
+use crate::backtest::parse_month_day;
+use crate::model::ProcessedBar;
+use crate::processor::{close_series, mean};
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+/// Historical behaviour of one calendar window across years, in percent. For short windows returns are
+/// sign-flipped, so falls count as wins.
+#[derive(Serialize, Clone)]
+pub struct WindowStats {
+    pub years: usize,
+    pub mean_return: f64,
+    pub median_return: f64,
+    pub win_rate: f64,
+    pub std_dev: f64,
+    /// Mean return divided by its standard deviation across years; higher means a steadier tendency.
+    /// Infinite, with the sign of the mean, when every year returned the same (sent to the frontend as
+    /// `null`); `None` for a single year or a flat mean.
+    pub consistency: Option<f64>,
+    pub best: f64,
+    pub worst: f64,
+}
+
+/// `month`/`day` clamped to the last day of the month, so 02-29 maps to 02-28 in common years.
+fn calendar_date(year: i32, month: u32, day: u32) -> Option<NaiveDate> {
+    (1..=day).rev().find_map(|d| NaiveDate::from_ymd_opt(year, month, d))
+}
+
+/// Return of holding from the first session on or after `start` for `days` calendar days, once per year.
+/// Years without a session inside the window are skipped.
+pub fn window_returns(series: &[(NaiveDate, f64)], start: (u32, u32), days: u32) -> Vec<(i32, f64)> {
+    let (Some(first), Some(last)) = (series.first(), series.last()) else { return vec![] };
+    (first.0.year()..=last.0.year())
+        .filter_map(|year| {
+            let target = calendar_date(year, start.0, start.1)?;
+            let exit_target = target + Duration::days(days as i64);
+            let entry = series.partition_point(|(d, _)| *d < target);
+            let exit = series.partition_point(|(d, _)| *d <= exit_target).checked_sub(1)?;
+            if entry >= series.len() || exit <= entry || series[entry].0 >= exit_target || series[entry].1 == 0.0 {
+                return None;
+            }
+            Some((year, (series[exit].1 / series[entry].1 - 1.0) * 100.0))
+        })
+        .collect()
+}
+
+pub fn window_stats(returns: &[f64], short: bool) -> Option<WindowStats> {
+    let signed: Vec<f64> = returns.iter().map(|r| if short { -r } else { *r }).collect();
+    let mean_return = mean(&signed)?;
+    let mut sorted = signed.clone();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let n = sorted.len();
+    let median_return = if n % 2 == 1 { sorted[n / 2] } else { (sorted[n / 2 - 1] + sorted[n / 2]) / 2.0 };
+    let std_dev = if n > 1 { (signed.iter().map(|r| (r - mean_return).powi(2)).sum::<f64>() / (n - 1) as f64).sqrt() } else { 0.0 };
+    Some(WindowStats {
+        years: n,
+        mean_return,
+        median_return,
+        win_rate: signed.iter().filter(|r| **r > 0.0).count() as f64 / n as f64 * 100.0,
+        std_dev,
+        consistency: if std_dev > 0.0 {
+            Some(mean_return / std_dev)
+        } else if n > 1 && mean_return != 0.0 {
+            Some(f64::INFINITY.copysign(mean_return))
+        } else {
+            None
+        },
+        best: sorted[n - 1],
+        worst: sorted[0],
+    })
+}
+
+#[derive(Deserialize)]
+pub struct ScreenerConfig {
+    /// Window start as "MM-DD"; defaults to today.
+    #[serde(default = "today")]
+    pub start: String,
+    #[serde(default = "default_days")]
+    pub days: u32,
+    #[serde(default = "default_min_years")]
+    pub min_years: usize,
+    #[serde(default)]
+    pub short: bool,
+}
+
+fn today() -> String {
+    chrono::Local::now().format("%m-%d").to_string()
+}
+
+fn default_days() -> u32 {
+    30
+}
+
+fn default_min_years() -> usize {
+    5
+}
+
+#[derive(Serialize)]
+pub struct ScreenerRow {
+    pub asset: String,
+    #[serde(flatten)]
+    pub stats: WindowStats,
+    pub mean_return_rank: usize,
+    pub win_rate_rank: usize,
+    pub consistency_rank: usize,
+    /// Average of the three ranks; rows are returned in this order.
+    pub overall_rank: f64,
+}
+
+#[derive(Serialize)]
+pub struct ScreenerResult {
+    pub start: String,
+    pub days: u32,
+    pub short: bool,
+    pub rows: Vec<ScreenerRow>,
+    /// Assets left out for having fewer than `min_years` years of the window.
+    pub skipped: Vec<String>,
+}
+
+/// 1-based rank of each item by `key`, highest first.
+fn ranks(rows: &[(String, WindowStats)], key: impl Fn(&WindowStats) -> f64) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..rows.len()).collect();
+    order.sort_by(|a, b| key(&rows[*b].1).partial_cmp(&key(&rows[*a].1)).unwrap_or(std::cmp::Ordering::Equal));
+    let mut out = vec![0; rows.len()];
+    for (rank, idx) in order.into_iter().enumerate() {
+        out[idx] = rank + 1;
+    }
+    out
+}
+
+/// Ranks assets by how they have historically behaved over the same forward calendar window.
+pub fn screen(assets: &[(String, Vec<ProcessedBar>)], config: &ScreenerConfig) -> anyhow::Result<ScreenerResult> {
+    let start = parse_month_day(&config.start)?;
+    let mut scored: Vec<(String, WindowStats)> = Vec::new();
+    let mut skipped = Vec::new();
+    for (asset, rows) in assets {
+        let returns: Vec<f64> = window_returns(&close_series(rows), start, config.days).into_iter().map(|(_, r)| r).collect();
+        match window_stats(&returns, config.short) {
+            Some(stats) if stats.years >= config.min_years.max(1) => scored.push((asset.clone(), stats)),
+            _ => skipped.push(asset.clone()),
+        }
+    }
+
+    let by_mean = ranks(&scored, |s| s.mean_return);
+    let by_win = ranks(&scored, |s| s.win_rate);
+    let by_consistency = ranks(&scored, |s| s.consistency.unwrap_or(f64::NEG_INFINITY));
+    let mut rows: Vec<ScreenerRow> = scored
+        .into_iter()
+        .enumerate()
+        .map(|(i, (asset, stats))| ScreenerRow {
+            asset,
+            stats,
+            mean_return_rank: by_mean[i],
+            win_rate_rank: by_win[i],
+            consistency_rank: by_consistency[i],
+            overall_rank: (by_mean[i] + by_win[i] + by_consistency[i]) as f64 / 3.0,
+        })
+        .collect();
+    rows.sort_by(|a, b| a.overall_rank.partial_cmp(&b.overall_rank).unwrap_or(std::cmp::Ordering::Equal));
+
+    Ok(ScreenerResult { start: config.start.clone(), days: config.days, short: config.short, rows, skipped })
+}
+
+/// Bounds for the best-window search. Dates are "MM-DD"; a start range may wrap past year end.
+#[derive(Deserialize)]
+pub struct WindowSearch {
+    #[serde(default = "default_earliest_start")]
+    pub earliest_start: String,
+    #[serde(default = "default_latest_start")]
+    pub latest_start: String,
+    #[serde(default = "default_min_hold")]
+    pub min_days: u32,
+    #[serde(default = "default_max_hold")]
+    pub max_days: u32,
+    /// Windows seen in fewer years are ignored.
+    #[serde(default = "default_min_years")]
+    pub min_years: usize,
+    #[serde(default)]
+    pub min_win_rate: f64,
+    #[serde(default)]
+    pub short: bool,
+    #[serde(default = "default_top_n")]
+    pub top_n: usize,
+    /// Skip windows that overlap an already reported one by more than half, so the lists are not
+    /// ten shifts of the same window.
+    #[serde(default = "default_distinct")]
+    pub distinct: bool,
+}
+
+fn default_earliest_start() -> String {
+    "01-01".to_string()
+}
+
+fn default_latest_start() -> String {
+    "12-31".to_string()
+}
+
+/// Longest holding period the window search accepts: one calendar year.
+const MAX_HOLD_DAYS: u32 = 366;
+
+fn default_min_hold() -> u32 {
+    5
+}
+
+fn default_max_hold() -> u32 {
+    60
+}
+
+fn default_top_n() -> usize {
+    10
+}
+
+fn default_distinct() -> bool {
+    true
+}
+
+#[derive(Serialize, Clone)]
+pub struct SeasonalWindow {
+    pub start: String,
+    /// Calendar exit date of the window (in a common year).
+    pub end: String,
+    pub days: u32,
+    #[serde(flatten)]
+    pub stats: WindowStats,
+    #[serde(skip)]
+    start_ordinal: u32,
+}
+
+#[derive(Serialize)]
+pub struct WindowSearchResult {
+    /// Start/holding combinations that passed the sample filters.
+    pub evaluated: usize,
+    pub by_win_rate: Vec<SeasonalWindow>,
+    pub by_mean_return: Vec<SeasonalWindow>,
+}
+
+/// Days shared by two windows on the circular calendar.
+fn overlap_days(a: &SeasonalWindow, b: &SeasonalWindow) -> i64 {
+    let (a0, a1) = (a.start_ordinal as i64, a.start_ordinal as i64 + a.days as i64);
+    [-365, 0, 365]
+        .iter()
+        .map(|shift| {
+            let (b0, b1) = (b.start_ordinal as i64 + shift, b.start_ordinal as i64 + b.days as i64 + shift);
+            (a1.min(b1) - a0.max(b0)).max(0)
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+fn top(candidates: &[SeasonalWindow], search: &WindowSearch, order: impl Fn(&SeasonalWindow, &SeasonalWindow) -> std::cmp::Ordering) -> Vec<SeasonalWindow> {
+    let mut sorted: Vec<&SeasonalWindow> = candidates.iter().collect();
+    sorted.sort_by(|a, b| order(a, b));
+    let mut picked: Vec<SeasonalWindow> = Vec::new();
+    for w in sorted {
+        if picked.len() >= search.top_n {
+            break;
+        }
+        if search.distinct && picked.iter().any(|p| overlap_days(p, w) * 2 > p.days.min(w.days) as i64) {
+            continue;
+        }
+        picked.push(w.clone());
+    }
+    picked
+}
+
+/// Scans every start date and holding period inside the bounds and reports the strongest windows.
+pub fn find_best_windows(input: &[ProcessedBar], search: &WindowSearch) -> anyhow::Result<WindowSearchResult> {
+    let (earliest, latest) = (parse_month_day(&search.earliest_start)?, parse_month_day(&search.latest_start)?);
+    if search.min_days == 0 || search.min_days > search.max_days {
+        return Err(anyhow::anyhow!("Holding period bounds must satisfy 0 < min_days <= max_days"));
+    }
+    if search.max_days > MAX_HOLD_DAYS {
+        return Err(anyhow::anyhow!("Holding periods are limited to {} days", MAX_HOLD_DAYS));
+    }
+    // A common year as the reference calendar for start dates.
+    let reference = |(m, d): (u32, u32)| calendar_date(2001, m, d).map(|date| date.ordinal()).unwrap_or(1);
+    let (first, last) = (reference(earliest), reference(latest));
+    let starts: Vec<u32> = if first <= last { (first..=last).collect() } else { (first..=365).chain(1..=last).collect() };
+
+    let series = close_series(input);
+    let mut candidates = Vec::new();
+    for ordinal in starts {
+        let Some(start_date) = NaiveDate::from_yo_opt(2001, ordinal) else { continue };
+        let start = (start_date.month(), start_date.day());
+        for days in search.min_days..=search.max_days {
+            let returns: Vec<f64> = window_returns(&series, start, days).into_iter().map(|(_, r)| r).collect();
+            let Some(stats) = window_stats(&returns, search.short) else { continue };
+            if stats.years < search.min_years.max(1) || stats.win_rate < search.min_win_rate {
+                continue;
+            }
+            candidates.push(SeasonalWindow {
+                start: start_date.format("%m-%d").to_string(),
+                end: (start_date + Duration::days(days as i64)).format("%m-%d").to_string(),
+                days,
+                stats,
+                start_ordinal: ordinal,
+            });
+        }
+    }
+
+    let cmp = |a: f64, b: f64| b.partial_cmp(&a).unwrap_or(std::cmp::Ordering::Equal);
+    let by_win_rate = top(&candidates, search, |a, b| {
+        cmp(a.stats.win_rate, b.stats.win_rate).then(cmp(a.stats.mean_return, b.stats.mean_return))
+    });
+    let by_mean_return = top(&candidates, search, |a, b| {
+        cmp(a.stats.mean_return, b.stats.mean_return).then(cmp(a.stats.win_rate, b.stats.win_rate))
+    });
+
+    Ok(WindowSearchResult { evaluated: candidates.len(), by_win_rate, by_mean_return })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::test_bar;
+
+    /// Closes rising through each January and flat otherwise, for `years` years.
+    fn january_rally(years: i32) -> Vec<ProcessedBar> {
+        let mut out = Vec::new();
+        for year in 2010..2010 + years {
+            let mut date = NaiveDate::from_ymd_opt(year, 1, 1).unwrap();
+            while date.year() == year {
+                let close = if date.month() == 1 { 100.0 + date.day() as f64 } else { 131.0 };
+                out.push(test_bar(&date.format("%Y-%m-%d").to_string(), close));
+                date += Duration::days(1);
+            }
+        }
+        out
+    }
+
+    /// A close on Jan 1 and one on Jan 31 per year, the second `gain` percent higher.
+    fn january_gains(gains: &[f64]) -> Vec<ProcessedBar> {
+        gains
+            .iter()
+            .enumerate()
+            .flat_map(|(i, gain)| {
+                let year = 2010 + i;
+                [test_bar(&format!("{}-01-01", year), 100.0), test_bar(&format!("{}-01-31", year), 100.0 + gain)]
+            })
+            .collect()
+    }
+
+    fn search(json: serde_json::Value) -> WindowSearch {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn rejects_holding_periods_over_a_year() {
+        let err = find_best_windows(&january_rally(3), &search(serde_json::json!({ "max_days": u32::MAX }))).err().unwrap();
+        assert!(err.to_string().contains("366"));
+        assert!(find_best_windows(&january_rally(3), &search(serde_json::json!({ "min_days": 366, "max_days": 366, "min_years": 1 }))).is_ok());
+    }
+
+    #[test]
+    fn finds_the_rally_window() {
+        let result = find_best_windows(
+            &january_rally(6),
+            &search(serde_json::json!({ "earliest_start": "12-01", "latest_start": "02-28", "min_days": 20, "max_days": 30, "top_n": 1 })),
+        )
+        .unwrap();
+        let best = &result.by_mean_return[0];
+        assert_eq!(best.start, "01-01");
+        assert_eq!(best.stats.win_rate, 100.0);
+    }
+
+    #[test]
+    fn overlap_wraps_around_year_end() {
+        let window = |start_ordinal, days| SeasonalWindow { start: String::new(), end: String::new(), days, stats: window_stats(&[1.0], false).unwrap(), start_ordinal };
+        assert_eq!(overlap_days(&window(360, 10), &window(2, 10)), 3);
+        assert_eq!(overlap_days(&window(10, 5), &window(100, 5)), 0);
+        assert_eq!(overlap_days(&window(u32::MAX - 1, u32::MAX), &window(1, 1)), 0);
+    }
+
+    #[test]
+    fn screen_orders_assets_by_average_rank() {
+        let assets = vec![
+            ("RALLY".to_string(), january_rally(6)),
+            ("STEADY".to_string(), january_gains(&[10.0, 12.0, 8.0, 10.0, 11.0, 9.0])),
+            ("MIXED".to_string(), january_gains(&[40.0, -5.0, 30.0, -5.0, 20.0, -5.0])),
+            ("NEW".to_string(), january_gains(&[50.0, 50.0])),
+        ];
+        let config: ScreenerConfig = serde_json::from_value(serde_json::json!({ "start": "01-01", "days": 30 })).unwrap();
+        let result = screen(&assets, &config).unwrap();
+        assert_eq!(result.skipped, vec!["NEW"]);
+        let ranks: Vec<(&str, usize, usize, usize)> =
+            result.rows.iter().map(|r| (r.asset.as_str(), r.mean_return_rank, r.win_rate_rank, r.consistency_rank)).collect();
+        // RALLY gains the same every year: no spread at all is the steadiest tendency there is.
+        assert_eq!(ranks, vec![("RALLY", 1, 1, 1), ("STEADY", 3, 2, 2), ("MIXED", 2, 3, 3)]);
+        assert!((result.rows[1].stats.mean_return - 10.0).abs() < 1e-9);
+        assert_eq!(result.rows[0].stats.consistency, Some(f64::INFINITY));
+
+        let short: ScreenerConfig = serde_json::from_value(serde_json::json!({ "start": "01-01", "days": 30, "short": true })).unwrap();
+        let result = screen(&assets[1..3], &short).unwrap();
+        assert_eq!(result.rows[0].asset, "MIXED");
+        assert_eq!(result.rows[0].stats.win_rate, 50.0);
+        assert_eq!(result.rows[1].stats.win_rate, 0.0);
+    }
+
+    #[test]
+    fn zero_spread_consistency_follows_the_mean() {
+        assert_eq!(window_stats(&[2.0, 2.0, 2.0], false).unwrap().consistency, Some(f64::INFINITY));
+        assert_eq!(window_stats(&[2.0, 2.0, 2.0], true).unwrap().consistency, Some(f64::NEG_INFINITY));
+        assert_eq!(window_stats(&[0.0, 0.0], false).unwrap().consistency, None);
+        assert_eq!(window_stats(&[2.0], false).unwrap().consistency, None);
+    }
+}
@@ -0,0 +1,155 @@
+use crate::model::{OhlcBar, ProcessedBar};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BasketLeg {
+    pub asset: String,
+    pub weight: f64,
+}
+
+/// A synthetic asset computed from stored assets on the dates they all share.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SyntheticFormula {
+    /// `a - b`, e.g. a calendar or crack spread leg pair.
+    Difference { a: String, b: String },
+    /// `numerator / denominator`, e.g. gold/silver.
+    Ratio { numerator: String, denominator: String },
+    /// Sum of `weight * price` over the legs; negative weights short a leg.
+    Basket { legs: Vec<BasketLeg> },
+}
+
+impl SyntheticFormula {
+    pub fn components(&self) -> Vec<&str> {
+        match self {
+            SyntheticFormula::Difference { a, b } => vec![a, b],
+            SyntheticFormula::Ratio { numerator, denominator } => vec![numerator, denominator],
+            SyntheticFormula::Basket { legs } => legs.iter().map(|l| l.asset.as_str()).collect(),
+        }
+    }
+
+    fn combine(&self, values: &[f64]) -> Option<f64> {
+        let v = match self {
+            SyntheticFormula::Difference { .. } => values[0] - values[1],
+            SyntheticFormula::Ratio { .. } if values[1] == 0.0 => return None,
+            SyntheticFormula::Ratio { .. } => values[0] / values[1],
+            SyntheticFormula::Basket { legs } => legs.iter().zip(values).map(|(l, v)| l.weight * v).sum(),
+        };
+        if v.is_finite() { Some(v) } else { None }
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let components = self.components();
+        if components.is_empty() {
+            return Err(anyhow::anyhow!("A synthetic asset needs at least one component"));
+        }
+        if components.iter().any(|c| c.trim().is_empty()) {
+            return Err(anyhow::anyhow!("Component asset names cannot be empty"));
+        }
+        if let SyntheticFormula::Basket { legs } = self {
+            if legs.iter().any(|l| !l.weight.is_finite()) {
+                return Err(anyhow::anyhow!("Basket weights must be finite numbers"));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn prices(input: &[ProcessedBar]) -> BTreeMap<NaiveDate, (Option<f64>, f64)> {
+    input
+        .iter()
+        .filter_map(|row| Some((row.bar.parsed_date()?, (row.bar.open, row.bar.close?))))
+        .collect()
+}
+
+/// Evaluates the formula on every date where all components have a close. Open uses the components' opens
+/// when they all have one; High and Low are the larger and smaller of Open and Close, since intrabar
+/// extremes of the legs do not combine.
+pub fn build(formula: &SyntheticFormula, inputs: &HashMap<String, Vec<ProcessedBar>>) -> anyhow::Result<Vec<OhlcBar>> {
+    formula.validate()?;
+    let series: Vec<BTreeMap<NaiveDate, (Option<f64>, f64)>> = formula
+        .components()
+        .iter()
+        .map(|asset| {
+            inputs
+                .get(*asset)
+                .map(|rows| prices(rows))
+                .filter(|p| !p.is_empty())
+                .ok_or_else(|| anyhow::anyhow!("Component asset '{}' has no data", asset))
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    let mut out = Vec::new();
+    for date in series[0].keys() {
+        let Some(bars) = series.iter().map(|s| s.get(date).copied()).collect::<Option<Vec<_>>>() else { continue };
+        let closes: Vec<f64> = bars.iter().map(|b| b.1).collect();
+        let Some(close) = formula.combine(&closes) else { continue };
+        let open = bars.iter().map(|b| b.0).collect::<Option<Vec<f64>>>().and_then(|o| formula.combine(&o));
+
+        out.push(OhlcBar {
+            date: Some(date.format("%Y-%m-%d").to_string()),
+            open,
+            high: Some(open.map_or(close, |o| o.max(close))),
+            low: Some(open.map_or(close, |o| o.min(close))),
+            close: Some(close),
+            ..Default::default()
+        });
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::test_bar;
+
+    fn inputs() -> HashMap<String, Vec<ProcessedBar>> {
+        HashMap::from([
+            ("CL".to_string(), vec![test_bar("2024-01-02", 80.0), test_bar("2024-01-03", 82.0), test_bar("2024-01-04", 81.0)]),
+            // No bar on the 3rd, an extra one on the 5th.
+            ("HO".to_string(), vec![test_bar("2024-01-02", 100.0), test_bar("2024-01-04", 90.0), test_bar("2024-01-05", 95.0)]),
+        ])
+    }
+
+    fn closes(bars: &[OhlcBar]) -> Vec<(&str, f64)> {
+        bars.iter().map(|b| (b.date.as_deref().unwrap(), b.close.unwrap())).collect()
+    }
+
+    #[test]
+    fn spread_uses_the_dates_both_legs_share() {
+        let formula = SyntheticFormula::Difference { a: "HO".to_string(), b: "CL".to_string() };
+        let bars = build(&formula, &inputs()).unwrap();
+        assert_eq!(closes(&bars), vec![("2024-01-02", 20.0), ("2024-01-04", 9.0)]);
+        assert_eq!((bars[1].open, bars[1].high, bars[1].low), (Some(9.0), Some(9.0), Some(9.0)));
+    }
+
+    #[test]
+    fn ratio_skips_a_zero_denominator() {
+        let mut inputs = inputs();
+        inputs.get_mut("CL").unwrap()[0].bar.close = Some(0.0);
+        let formula = SyntheticFormula::Ratio { numerator: "HO".to_string(), denominator: "CL".to_string() };
+        let bars = build(&formula, &inputs).unwrap();
+        assert_eq!(bars.len(), 1);
+        assert_eq!(bars[0].date.as_deref(), Some("2024-01-04"));
+        assert!((bars[0].close.unwrap() - 90.0 / 81.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn basket_weights_legs_and_spans_the_open_close_range() {
+        let mut inputs = inputs();
+        inputs.get_mut("HO").unwrap()[0].bar.open = Some(104.0);
+        let legs = vec![BasketLeg { asset: "HO".to_string(), weight: 2.0 }, BasketLeg { asset: "CL".to_string(), weight: -1.0 }];
+        let bars = build(&SyntheticFormula::Basket { legs }, &inputs).unwrap();
+        assert_eq!(closes(&bars), vec![("2024-01-02", 120.0), ("2024-01-04", 99.0)]);
+        assert_eq!((bars[0].open, bars[0].high, bars[0].low), (Some(128.0), Some(128.0), Some(120.0)));
+    }
+
+    #[test]
+    fn missing_components_are_reported() {
+        let formula = SyntheticFormula::Difference { a: "HO".to_string(), b: "NG".to_string() };
+        assert!(build(&formula, &inputs()).unwrap_err().to_string().contains("'NG'"));
+        assert!(SyntheticFormula::Basket { legs: vec![] }.validate().is_err());
+    }
+}